@@ -0,0 +1,26 @@
+/*
+  # Column Mapping Profiles
+
+  Named layouts describing where cheque fields live in an imported
+  spreadsheet. `column_map` holds a JSON object keyed by cheque field, e.g.
+  {"cheque_number": {"header": "Cheque No"}, "amount": {"column": "C"}}
+*/
+
+CREATE TABLE IF NOT EXISTS mapping_profiles (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT UNIQUE NOT NULL,
+    sheet_name TEXT,
+    header_row INTEGER NOT NULL DEFAULT 1,
+    column_map TEXT NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_mapping_profiles_name ON mapping_profiles(name);
+
+CREATE TRIGGER IF NOT EXISTS update_mapping_profiles_timestamp
+AFTER UPDATE ON mapping_profiles
+FOR EACH ROW
+BEGIN
+    UPDATE mapping_profiles SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+END;
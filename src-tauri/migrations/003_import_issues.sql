@@ -0,0 +1,21 @@
+/*
+  # Import Issues
+
+  Rows rejected while importing a spreadsheet, kept against the document
+  they came from so they can be reviewed after the upload.
+*/
+
+CREATE TABLE IF NOT EXISTS import_issues (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    document_id INTEGER NOT NULL,
+    sheet_name TEXT NOT NULL,
+    row_number INTEGER NOT NULL,
+    column_name TEXT,
+    field TEXT,
+    reason TEXT NOT NULL,
+    cell_value TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (document_id) REFERENCES documents (id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_import_issues_document_id ON import_issues(document_id);
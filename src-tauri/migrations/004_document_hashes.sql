@@ -0,0 +1,11 @@
+/*
+  # Document Content Hashes
+
+  SHA-256 of each uploaded file, used to spot the same spreadsheet being
+  uploaded twice under different names. Existing rows are backfilled by the
+  application on startup.
+*/
+
+ALTER TABLE documents ADD COLUMN content_hash TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_documents_content_hash ON documents(content_hash);
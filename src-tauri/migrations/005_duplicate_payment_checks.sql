@@ -0,0 +1,30 @@
+/*
+  # Duplicate Payment Checks
+
+  Adds the paying account to each cheque, flags for suspected duplicate
+  payments and a key/value settings table holding the duplicate rules.
+*/
+
+-- Application settings stored as JSON values
+CREATE TABLE IF NOT EXISTS app_settings (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+ALTER TABLE cheques ADD COLUMN account_number TEXT;
+ALTER TABLE cheques ADD COLUMN duplicate_flag TEXT;
+ALTER TABLE cheques ADD COLUMN duplicate_override_reason TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_cheques_account_cheque_number ON cheques(account_number, cheque_number);
+CREATE INDEX IF NOT EXISTS idx_cheques_client_name_amount ON cheques(client_name, amount);
+
+CREATE TRIGGER IF NOT EXISTS update_app_settings_timestamp
+AFTER UPDATE ON app_settings
+FOR EACH ROW
+BEGIN
+    UPDATE app_settings SET updated_at = CURRENT_TIMESTAMP WHERE key = NEW.key;
+END;
+
+INSERT OR IGNORE INTO app_settings (key, value)
+VALUES ('duplicate_payment_rules', '{"same_cheque_number":true,"same_payee_amount_days":30}');
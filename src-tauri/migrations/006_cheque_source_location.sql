@@ -0,0 +1,9 @@
+/*
+  # Cheque Source Location
+
+  Records the worksheet and 1-based row each imported cheque was read from,
+  so cheques from multi-sheet workbooks can be traced back to their cell.
+*/
+
+ALTER TABLE cheques ADD COLUMN source_sheet TEXT;
+ALTER TABLE cheques ADD COLUMN source_row INTEGER;
@@ -0,0 +1,8 @@
+/*
+  # Cheque Memo
+
+  Adds the memo line printed on the cheque. Dates and internal references
+  from imported files use the existing issue_date, date_field and remarks columns.
+*/
+
+ALTER TABLE cheques ADD COLUMN memo TEXT;
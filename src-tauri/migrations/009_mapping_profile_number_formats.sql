@@ -0,0 +1,10 @@
+/*
+  # Mapping Profile Number Formats
+
+  Lets a profile say how amounts typed as text are written ("auto", "point"
+  for 1,234.50 or "comma" for 1.234,50) and how many digits cheque numbers
+  read from numeric cells are zero-padded to.
+*/
+
+ALTER TABLE mapping_profiles ADD COLUMN decimal_separator TEXT NOT NULL DEFAULT 'auto';
+ALTER TABLE mapping_profiles ADD COLUMN cheque_number_digits INTEGER;
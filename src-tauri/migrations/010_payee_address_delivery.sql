@@ -0,0 +1,14 @@
+/*
+  # Payee Address and Delivery
+
+  Adds the payee mailing address printed for window envelopes and how each
+  cheque is delivered. Mail and courier delivery need an address.
+*/
+
+ALTER TABLE cheques ADD COLUMN address_line1 TEXT;
+ALTER TABLE cheques ADD COLUMN address_line2 TEXT;
+ALTER TABLE cheques ADD COLUMN city TEXT;
+ALTER TABLE cheques ADD COLUMN region TEXT;
+ALTER TABLE cheques ADD COLUMN postal_code TEXT;
+ALTER TABLE cheques ADD COLUMN country TEXT;
+ALTER TABLE cheques ADD COLUMN delivery_method TEXT CHECK (delivery_method IN ('mail', 'pickup', 'courier'));
@@ -0,0 +1,103 @@
+/*
+  # Import Staging
+
+  Holds imported rows for review before they become cheques. Each staged row
+  keeps the cell values read from the spreadsheet alongside the values after
+  manual correction. The audit trail is rebuilt so corrections can be logged
+  against a staged row before any cheque exists; they are linked to the cheque
+  when the batch is committed.
+*/
+
+-- Upload waiting to be reviewed and committed
+CREATE TABLE IF NOT EXISTS staged_imports (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    file_name TEXT NOT NULL,
+    file_data BLOB NOT NULL,
+    content_hash TEXT NOT NULL,
+    -- Column mapping and import options in effect when the file was staged
+    mapping TEXT NOT NULL,
+    options TEXT NOT NULL,
+    skipped_sheets TEXT,
+    status TEXT NOT NULL DEFAULT 'staged' CHECK (status IN ('staged', 'committed', 'discarded')),
+    document_id INTEGER,
+    created_by INTEGER,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (document_id) REFERENCES documents (id) ON DELETE SET NULL
+);
+
+-- One non-blank spreadsheet row of a staged upload
+CREATE TABLE IF NOT EXISTS staged_rows (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    staged_import_id INTEGER NOT NULL,
+    sheet_name TEXT NOT NULL,
+    row_number INTEGER NOT NULL,
+    -- Column letter each field was read from
+    column_map TEXT NOT NULL,
+    original_values TEXT NOT NULL,
+    current_values TEXT NOT NULL,
+    -- Validation problems with the current values; NULL when the row is valid
+    issues TEXT,
+    is_deleted BOOLEAN NOT NULL DEFAULT 0,
+    cheque_id INTEGER,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (staged_import_id) REFERENCES staged_imports (id) ON DELETE CASCADE,
+    FOREIGN KEY (cheque_id) REFERENCES cheques (id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_staged_imports_status ON staged_imports(status);
+CREATE INDEX IF NOT EXISTS idx_staged_rows_staged_import_id ON staged_rows(staged_import_id);
+
+CREATE TRIGGER IF NOT EXISTS update_staged_imports_timestamp
+AFTER UPDATE ON staged_imports
+FOR EACH ROW
+BEGIN
+    UPDATE staged_imports SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS update_staged_rows_timestamp
+AFTER UPDATE ON staged_rows
+FOR EACH ROW
+BEGIN
+    UPDATE staged_rows SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+END;
+
+-- Rebuild the audit trail with an optional cheque and a staged row reference.
+-- Legacy rename keeps the cheque triggers and audit view pointing at audit_trail.
+PRAGMA legacy_alter_table = ON;
+
+CREATE TABLE audit_trail_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    cheque_id INTEGER,
+    staged_row_id INTEGER,
+    action_type TEXT NOT NULL,
+    old_value TEXT,
+    new_value TEXT,
+    user_id INTEGER DEFAULT 1,
+    user_name TEXT DEFAULT 'System',
+    timestamp DATETIME DEFAULT CURRENT_TIMESTAMP,
+    notes TEXT,
+    session_id TEXT,
+    ip_address TEXT,
+    FOREIGN KEY (cheque_id) REFERENCES cheques (id) ON DELETE CASCADE,
+    FOREIGN KEY (staged_row_id) REFERENCES staged_rows (id) ON DELETE SET NULL
+);
+
+INSERT INTO audit_trail_new (id, cheque_id, action_type, old_value, new_value, user_id, user_name,
+                             timestamp, notes, session_id, ip_address)
+SELECT id, cheque_id, action_type, old_value, new_value, user_id, user_name,
+       timestamp, notes, session_id, ip_address
+FROM audit_trail;
+
+DROP TABLE audit_trail;
+ALTER TABLE audit_trail_new RENAME TO audit_trail;
+
+PRAGMA legacy_alter_table = OFF;
+
+CREATE INDEX IF NOT EXISTS idx_audit_trail_cheque_id ON audit_trail(cheque_id);
+CREATE INDEX IF NOT EXISTS idx_audit_trail_staged_row_id ON audit_trail(staged_row_id);
+CREATE INDEX IF NOT EXISTS idx_audit_trail_timestamp ON audit_trail(timestamp);
+CREATE INDEX IF NOT EXISTS idx_audit_trail_action_type ON audit_trail(action_type);
+CREATE INDEX IF NOT EXISTS idx_audit_trail_user_id ON audit_trail(user_id);
+CREATE INDEX IF NOT EXISTS idx_audit_trail_session_id ON audit_trail(session_id);
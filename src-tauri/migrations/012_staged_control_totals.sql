@@ -0,0 +1,8 @@
+/*
+  # Staged Control Totals
+
+  Keeps the control totals read from a staged upload, such as its trailer
+  rows, so they can be checked again when the batch is committed.
+*/
+
+ALTER TABLE staged_imports ADD COLUMN declared_totals TEXT;
@@ -0,0 +1,12 @@
+/*
+  # Document Signatures
+
+  Records the result of checking an upload's detached Ed25519 or HMAC
+  signature against the trusted keys. Documents uploaded earlier have no status.
+*/
+
+ALTER TABLE documents ADD COLUMN signature_status TEXT CHECK (signature_status IN ('verified', 'unsigned', 'invalid'));
+ALTER TABLE documents ADD COLUMN signature_key TEXT;
+ALTER TABLE documents ADD COLUMN signature_algorithm TEXT;
+ALTER TABLE documents ADD COLUMN signature TEXT;
+ALTER TABLE documents ADD COLUMN signature_detail TEXT;
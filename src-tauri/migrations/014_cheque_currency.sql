@@ -0,0 +1,8 @@
+/*
+  # Cheque Currency
+
+  Records the ISO 4217 currency each cheque is issued in. Cheques issued
+  before currencies were recorded were all in US dollars.
+*/
+
+ALTER TABLE cheques ADD COLUMN currency TEXT NOT NULL DEFAULT 'USD';
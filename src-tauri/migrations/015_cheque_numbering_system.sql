@@ -0,0 +1,9 @@
+/*
+  # Cheque Numbering System
+
+  Lets a cheque override how its amount is written in words. When empty the
+  numbering system set for its account, or the default, is used.
+*/
+
+ALTER TABLE cheques ADD COLUMN numbering_system TEXT
+  CHECK(numbering_system IN ('international', 'british', 'american', 'indian'));
@@ -0,0 +1,111 @@
+/*
+  # Cheque Amounts in Minor Units
+
+  Amounts were stored as floating-point REAL values, which cannot hold most
+  decimal amounts exactly. Each amount is now a whole number of its currency's
+  minor units in `amount_minor`: cents for every currency registered when this
+  migration was written except JPY, which has none. The REAL column is dropped.
+
+  An amount that was not a whole number of minor units, such as 10.005, is
+  rounded to the nearest one and listed in `amount_conversion_issues` with its
+  original value for review.
+*/
+
+CREATE TABLE IF NOT EXISTS amount_conversion_issues (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    cheque_id INTEGER NOT NULL,
+    currency TEXT NOT NULL,
+    original_amount REAL NOT NULL,
+    amount_minor INTEGER NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (cheque_id) REFERENCES cheques (id) ON DELETE CASCADE
+);
+
+ALTER TABLE cheques ADD COLUMN amount_minor INTEGER NOT NULL DEFAULT 0;
+
+-- The conversion touches every cheque; keep updated_at and the processing
+-- metrics as they were
+DROP TRIGGER IF EXISTS update_cheques_timestamp;
+DROP TRIGGER IF EXISTS update_processing_metrics;
+
+UPDATE cheques
+SET amount_minor = CAST(ROUND(amount * CASE currency WHEN 'JPY' THEN 1 ELSE 100 END) AS INTEGER);
+
+INSERT INTO amount_conversion_issues (cheque_id, currency, original_amount, amount_minor)
+SELECT id, currency, amount, amount_minor
+FROM cheques
+WHERE ABS(amount * CASE currency WHEN 'JPY' THEN 1 ELSE 100 END - amount_minor) > 0.000001;
+
+DROP VIEW IF EXISTS cheque_summary;
+DROP TRIGGER IF EXISTS log_amount_change;
+DROP INDEX IF EXISTS idx_cheques_amount;
+DROP INDEX IF EXISTS idx_cheques_client_name_amount;
+
+ALTER TABLE cheques DROP COLUMN amount;
+
+CREATE INDEX IF NOT EXISTS idx_cheques_amount_minor ON cheques(amount_minor);
+CREATE INDEX IF NOT EXISTS idx_cheques_client_name_amount_minor ON cheques(client_name, amount_minor);
+
+CREATE TRIGGER IF NOT EXISTS update_cheques_timestamp
+AFTER UPDATE ON cheques
+FOR EACH ROW
+BEGIN
+    UPDATE cheques SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS update_processing_metrics
+AFTER UPDATE ON cheques
+FOR EACH ROW
+BEGIN
+    INSERT OR REPLACE INTO processing_metrics (
+        cheque_id,
+        uploaded_at,
+        first_signature_at,
+        approved_at,
+        declined_at,
+        total_processing_time,
+        status_changes_count
+    )
+    SELECT
+        NEW.id,
+        NEW.created_at,
+        CASE WHEN NEW.current_signatures >= 1 AND OLD.current_signatures < 1 THEN CURRENT_TIMESTAMP ELSE pm.first_signature_at END,
+        CASE WHEN NEW.status = 'Approve' AND OLD.status != 'Approve' THEN CURRENT_TIMESTAMP ELSE pm.approved_at END,
+        CASE WHEN NEW.status = 'Declined' AND OLD.status != 'Declined' THEN CURRENT_TIMESTAMP ELSE pm.declined_at END,
+        CASE
+            WHEN NEW.status IN ('Approve', 'Declined') THEN
+                ROUND((julianday(CURRENT_TIMESTAMP) - julianday(NEW.created_at)) * 24, 2)
+            ELSE pm.total_processing_time
+        END,
+        COALESCE(pm.status_changes_count, 0) + CASE WHEN OLD.status != NEW.status THEN 1 ELSE 0 END
+    FROM (SELECT * FROM processing_metrics WHERE cheque_id = NEW.id) pm
+    WHERE TRUE;
+END;
+
+CREATE TRIGGER IF NOT EXISTS log_amount_change
+AFTER UPDATE OF amount_minor ON cheques
+FOR EACH ROW
+WHEN OLD.amount_minor != NEW.amount_minor
+BEGIN
+    INSERT INTO audit_trail (cheque_id, action_type, old_value, new_value, timestamp, notes)
+    VALUES (NEW.id, 'amount_changed', CAST(OLD.amount_minor AS TEXT), CAST(NEW.amount_minor AS TEXT), CURRENT_TIMESTAMP,
+            'Amount changed from ' || OLD.amount_minor || ' to ' || NEW.amount_minor || ' ' || NEW.currency || ' minor units');
+END;
+
+CREATE VIEW IF NOT EXISTS cheque_summary AS
+SELECT
+    c.id,
+    c.cheque_number,
+    c.amount_minor,
+    c.currency,
+    c.client_name,
+    c.status,
+    c.created_at,
+    c.current_signatures,
+    c.required_signatures,
+    pm.total_processing_time,
+    pm.status_changes_count,
+    d.file_name as source_file
+FROM cheques c
+LEFT JOIN processing_metrics pm ON c.id = pm.cheque_id
+LEFT JOIN documents d ON c.document_id = d.id;
@@ -0,0 +1,25 @@
+/*
+  # Trusted Key Encodings
+
+  Keys used to be read as hex whenever they looked like hex and as base64
+  otherwise, so a base64 key made only of hex digits was misread. Each trusted
+  key now names its encoding; existing keys keep the one they were read with.
+*/
+
+UPDATE app_settings
+SET value = json_set(value, '$.trusted_keys', json((
+    SELECT json_group_array(json_set(
+        json(k.value),
+        '$.encoding',
+        CASE
+            WHEN length(replace(replace(replace(replace(json_extract(k.value, '$.key'), ' ', ''), char(9), ''), char(10), ''), char(13), '')) % 2 = 0
+             AND replace(replace(replace(replace(json_extract(k.value, '$.key'), ' ', ''), char(9), ''), char(10), ''), char(13), '') NOT GLOB '*[^0-9A-Fa-f]*'
+             AND json_extract(k.value, '$.key') <> ''
+            THEN 'hex'
+            ELSE 'base64'
+        END
+    ))
+    FROM json_each(app_settings.value, '$.trusted_keys') AS k
+)))
+WHERE key = 'file_signatures'
+  AND json_type(value, '$.trusted_keys') = 'array';
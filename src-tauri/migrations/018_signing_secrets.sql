@@ -0,0 +1,35 @@
+/*
+  # Signing Secrets
+
+  HMAC shared secrets for signed payment files were stored in plain text with
+  the rest of the signature policy in `app_settings`, where the settings
+  screen and ad hoc queries could read them. They move to `signing_secrets`,
+  which only the import code reads, and are blanked in the stored policy.
+*/
+
+CREATE TABLE IF NOT EXISTS signing_secrets (
+    key_name TEXT PRIMARY KEY,
+    secret TEXT NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT OR REPLACE INTO signing_secrets (key_name, secret)
+SELECT json_extract(k.value, '$.name'), json_extract(k.value, '$.key')
+FROM app_settings, json_each(app_settings.value, '$.trusted_keys') AS k
+WHERE app_settings.key = 'file_signatures'
+  AND json_extract(k.value, '$.algorithm') = 'hmac_sha256'
+  AND json_extract(k.value, '$.key') IS NOT NULL;
+
+UPDATE app_settings
+SET value = json_set(value, '$.trusted_keys', json((
+    SELECT json_group_array(
+        CASE
+            WHEN json_extract(k.value, '$.algorithm') = 'hmac_sha256'
+            THEN json_set(json(k.value), '$.key', '', '$.has_secret', json('true'))
+            ELSE json(k.value)
+        END
+    )
+    FROM json_each(app_settings.value, '$.trusted_keys') AS k
+)))
+WHERE key = 'file_signatures'
+  AND json_type(value, '$.trusted_keys') = 'array';
@@ -0,0 +1,47 @@
+/*
+  # Cheque Analytics in Minor Units
+
+  Pre-computed totals in `cheque_analytics` were REAL amounts added across
+  every currency. Each row now covers one currency, and its total, largest and
+  smallest amounts are whole numbers of that currency's minor units, as cheque
+  amounts are.
+
+  Existing rows do not record a currency. They are kept as USD, the currency
+  of cheques that do not name one, with amounts rounded to whole cents.
+*/
+
+CREATE TABLE cheque_analytics_minor (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    period_type TEXT NOT NULL,
+    period_date TEXT NOT NULL,
+    currency TEXT NOT NULL DEFAULT 'USD',
+    total_cheques INTEGER DEFAULT 0,
+    total_amount_minor INTEGER DEFAULT 0,
+    approved_count INTEGER DEFAULT 0,
+    declined_count INTEGER DEFAULT 0,
+    pending_count INTEGER DEFAULT 0,
+    avg_processing_time REAL DEFAULT 0,
+    max_amount_minor INTEGER DEFAULT 0,
+    min_amount_minor INTEGER DEFAULT 0,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(period_type, period_date, currency)
+);
+
+INSERT INTO cheque_analytics_minor (
+    id, period_type, period_date, currency, total_cheques, total_amount_minor,
+    approved_count, declined_count, pending_count, avg_processing_time,
+    max_amount_minor, min_amount_minor, updated_at
+)
+SELECT
+    id, period_type, period_date, 'USD', total_cheques, CAST(ROUND(COALESCE(total_amount, 0) * 100) AS INTEGER),
+    approved_count, declined_count, pending_count, avg_processing_time,
+    CAST(ROUND(COALESCE(max_amount, 0) * 100) AS INTEGER), CAST(ROUND(COALESCE(min_amount, 0) * 100) AS INTEGER), updated_at
+FROM cheque_analytics;
+
+DROP INDEX IF EXISTS idx_cheque_analytics_period;
+DROP INDEX IF EXISTS idx_cheque_analytics_updated_at;
+DROP TABLE cheque_analytics;
+ALTER TABLE cheque_analytics_minor RENAME TO cheque_analytics;
+
+CREATE INDEX IF NOT EXISTS idx_cheque_analytics_period ON cheque_analytics(period_type, period_date, currency);
+CREATE INDEX IF NOT EXISTS idx_cheque_analytics_updated_at ON cheque_analytics(updated_at);
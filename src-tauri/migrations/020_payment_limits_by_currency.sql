@@ -0,0 +1,40 @@
+/*
+  # Payment Limits by Currency
+
+  Payment limits were plain amounts applied to cheques in every currency, so
+  a limit meant for dollars also capped yen. Each limit is now keyed by ISO
+  currency code and only applies to cheques in that currency.
+
+  Stored limits become limits in USD, the currency of cheques that do not name
+  one. Every other registered currency gets the default maximum of 25,000,000,
+  as does USD when no overall maximum was set, so cheques can still be written
+  in every currency.
+*/
+
+UPDATE app_settings
+SET value = json_object(
+    'max_amount', json_object(
+        'USD', CASE
+            WHEN json_type(value, '$.max_amount') IN ('integer', 'real', 'text')
+                THEN json_extract(value, '$.max_amount')
+            ELSE '25000000'
+        END,
+        'CAD', '25000000',
+        'EUR', '25000000',
+        'GBP', '25000000',
+        'TTD', '25000000',
+        'JPY', '25000000'
+    ),
+    'account_limits', json(COALESCE(
+        (SELECT json_group_object(a.key, json_object('USD', a.value))
+         FROM json_each(app_settings.value, '$.account_limits') AS a),
+        '{}'
+    )),
+    'role_limits', json(COALESCE(
+        (SELECT json_group_object(r.key, json_object('USD', r.value))
+         FROM json_each(app_settings.value, '$.role_limits') AS r),
+        '{}'
+    ))
+)
+WHERE key = 'payment_limits'
+  AND json_type(value, '$.max_amount') IS NOT 'object';
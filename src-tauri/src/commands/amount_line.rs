@@ -0,0 +1,142 @@
+use serde::{Deserialize, Serialize};
+
+/// Lines the amount may wrap over when the format does not say
+pub const DEFAULT_MAX_LINES: usize = 2;
+
+/// Letter case of the amount in words
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LetterCase {
+    /// As the language writes it: title case in English, lower case in French and Spanish
+    #[default]
+    Natural,
+    /// Only the first letter capitalised: "One thousand two hundred dollars"
+    Sentence,
+    /// All capitals: "ONE THOUSAND TWO HUNDRED DOLLARS"
+    Upper,
+}
+
+impl LetterCase {
+    pub fn apply(&self, text: &str) -> String {
+        match self {
+            LetterCase::Natural => text.to_string(),
+            LetterCase::Sentence => {
+                let lower = text.to_lowercase();
+                let mut chars = lower.chars();
+                match chars.next() {
+                    Some(first) => first.to_uppercase().chain(chars).collect(),
+                    None => lower,
+                }
+            }
+            LetterCase::Upper => text.to_uppercase(),
+        }
+    }
+}
+
+/// How the minor units of an amount are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MinorUnitStyle {
+    /// In words with the unit's name: "and Forty-Five Cents"
+    #[default]
+    Words,
+    /// As a fraction of the major unit: "and 45/100"
+    Fraction,
+}
+
+/// How the amount in words is laid out on the cheque face
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AmountLineFormat {
+    pub letter_case: LetterCase,
+    pub minor_units: MinorUnitStyle,
+    /// Close the amount with "Only", in each language it is written in
+    pub only: bool,
+    /// Characters that fit on one amount line of the cheque stock. Without it
+    /// the amount is a single line of any length.
+    pub line_width: Option<usize>,
+    /// Lines the amount may wrap over, `DEFAULT_MAX_LINES` when empty
+    pub max_lines: Option<usize>,
+    /// Character filling each line up to `line_width`, such as '*', so nothing
+    /// can be written after the amount
+    pub fill: Option<char>,
+}
+
+impl AmountLineFormat {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.line_width == Some(0) {
+            return Err("Amount line width must be at least 1 character".to_string());
+        }
+        if self.max_lines == Some(0) {
+            return Err("Amount must be allowed at least 1 line".to_string());
+        }
+        match self.fill {
+            Some(fill) if fill.is_whitespace() => {
+                Err("Protective fill must be a visible character, such as *".to_string())
+            }
+            Some(_) if self.line_width.is_none() => {
+                Err("Set the amount line width to fill lines up to it".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Wrap `words` to the line width at word boundaries and fill each line,
+    /// failing when they do not fit on the lines available
+    pub fn lay_out(&self, words: &str) -> Result<Vec<String>, String> {
+        let Some(width) = self.line_width else {
+            return Ok(vec![words.to_string()]);
+        };
+        let max_lines = self.max_lines.unwrap_or(DEFAULT_MAX_LINES);
+        let lines = wrap_words(words, width);
+        if lines.len() > max_lines || lines.iter().any(|line| line.chars().count() > width) {
+            return Err(format!(
+                "Amount in words does not fit on {} line{} of {} characters",
+                max_lines,
+                if max_lines == 1 { "" } else { "s" },
+                width
+            ));
+        }
+
+        Ok(match self.fill {
+            Some(fill) => lines
+                .into_iter()
+                .map(|line| {
+                    let padding = width - line.chars().count();
+                    line + &fill.to_string().repeat(padding)
+                })
+                .collect(),
+            None => lines,
+        })
+    }
+}
+
+/// Amount as printed on the cheque face
+#[derive(Debug, Clone, Serialize)]
+pub struct AmountLine {
+    pub payee: String,
+    /// Amount in words on one line, without fill
+    pub words: String,
+    /// Amount in words as printed: wrapped to the line width and filled
+    pub lines: Vec<String>,
+}
+
+/// Break `text` at spaces into lines of at most `width` characters. A word
+/// longer than `width` is left on a line of its own.
+pub fn wrap_words(text: &str, width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for word in text.split_whitespace() {
+        if !current.is_empty() && current.chars().count() + 1 + word.chars().count() > width {
+            lines.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+    if !current.is_empty() {
+        lines.push(current);
+    }
+    lines
+}
@@ -0,0 +1,248 @@
+use std::str::FromStr;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use super::currency::find_currency;
+
+/// Currency symbols that may surround an amount typed as text
+const CURRENCY_SYMBOLS: &[char] = &[
+    '$', '€', '£', '¥', '₹', '¢', '₩', '₽', '₺', '₪', '₫', '₱', '฿', '₦', '₴', '₲', '₵', '₡', '₸',
+];
+
+/// How the decimal point is written in amounts stored as text
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DecimalSeparator {
+    /// Decide per value. A single separator followed by exactly three digits is
+    /// read as digit grouping, so "1,234" and "1.234" are both 1234.
+    #[default]
+    Auto,
+    /// "1,234.50"
+    Point,
+    /// "1.234,50" or "1 234,50"
+    Comma,
+}
+
+impl DecimalSeparator {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DecimalSeparator::Auto => "auto",
+            DecimalSeparator::Point => "point",
+            DecimalSeparator::Comma => "comma",
+        }
+    }
+}
+
+impl FromStr for DecimalSeparator {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "auto" => Ok(DecimalSeparator::Auto),
+            "point" => Ok(DecimalSeparator::Point),
+            "comma" => Ok(DecimalSeparator::Comma),
+            other => Err(format!("Unknown decimal separator '{}'", other)),
+        }
+    }
+}
+
+/// Parse an amount typed as text, such as "$1,234.50", "1.234,50", "(250.00)"
+/// or "1 250,00 €". Spaces and apostrophes are ignored, as is one currency
+/// symbol or code of a known currency at each end. Parentheses or a leading or
+/// trailing minus make the amount negative; only one sign marker is accepted.
+pub fn parse_amount_text(text: &str, separator: DecimalSeparator) -> Result<Decimal, String> {
+    let invalid = || format!("'{}' is not a valid amount", text.trim());
+
+    let compact: String = text
+        .chars()
+        .filter(|c| !c.is_whitespace() && !matches!(c, '\'' | '’'))
+        .collect();
+    let (negative, number) = strip_sign_and_currency(&compact).ok_or_else(invalid)?;
+
+    if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit() || c == '.' || c == ',') {
+        return Err(invalid());
+    }
+
+    let (decimal, group) = match separator {
+        DecimalSeparator::Point => ('.', Some(',')),
+        DecimalSeparator::Comma => (',', Some('.')),
+        DecimalSeparator::Auto => detect_separators(number),
+    };
+
+    let (integer_part, fraction) = match number.split_once(decimal) {
+        Some((integer_part, fraction)) => (integer_part, fraction),
+        None => (number, ""),
+    };
+    if fraction.contains(['.', ',']) {
+        return Err(invalid());
+    }
+
+    let digits = match group {
+        Some(group) if integer_part.contains(group) => ungroup(integer_part, group).ok_or_else(invalid)?,
+        _ if integer_part.contains(['.', ',']) => return Err(invalid()),
+        _ => integer_part.to_string(),
+    };
+    if digits.is_empty() && fraction.is_empty() {
+        return Err(invalid());
+    }
+
+    let value = Decimal::from_str_exact(
+        format!("{}.{}", if digits.is_empty() { "0" } else { &digits }, fraction).trim_end_matches('.'),
+    )
+    .map_err(|_| invalid())?;
+
+    Ok(if negative { -value } else { value })
+}
+
+/// Exact decimal value of a spreadsheet number. Spreadsheets hold numbers as
+/// doubles and show them to 15 significant digits, so 0.1 is read as 0.1
+/// rather than the nearest double's full binary expansion.
+pub fn decimal_from_float(value: f64) -> Result<Decimal, String> {
+    Decimal::from_f64_retain(value)
+        .and_then(|decimal| decimal.round_sf(15))
+        .map(|decimal| decimal.normalize())
+        .ok_or_else(|| format!("'{}' is not a valid amount", value))
+}
+
+/// Peel sign markers and currency symbols or codes off both ends, in any
+/// order. `None` when there is more than one sign marker, such as "--5" or "-(5)".
+fn strip_sign_and_currency(text: &str) -> Option<(bool, &str)> {
+    let (mut signs, mut negative) = (0, false);
+    let (mut leading_currency, mut trailing_currency) = (false, false);
+    let mut rest = text;
+    loop {
+        if let Some(inner) = rest.strip_prefix('(').and_then(|r| r.strip_suffix(')')) {
+            (signs, negative, rest) = (signs + 1, true, inner);
+        } else if let Some(inner) = rest.strip_prefix('-').or_else(|| rest.strip_suffix('-')) {
+            (signs, negative, rest) = (signs + 1, true, inner);
+        } else if let Some(inner) = rest.strip_prefix('+') {
+            (signs, rest) = (signs + 1, inner);
+        } else if let Some(inner) = strip_currency_prefix(rest).filter(|_| !leading_currency) {
+            (leading_currency, rest) = (true, inner);
+        } else if let Some(inner) = strip_currency_suffix(rest).filter(|_| !trailing_currency) {
+            (trailing_currency, rest) = (true, inner);
+        } else {
+            break;
+        }
+    }
+    (signs <= 1).then_some((negative, rest))
+}
+
+fn strip_currency_prefix(text: &str) -> Option<&str> {
+    let first = text.chars().next()?;
+    if CURRENCY_SYMBOLS.contains(&first) {
+        return Some(&text[first.len_utf8()..]);
+    }
+    let code = text.get(..3)?;
+    is_currency_code(code).then(|| &text[3..])
+}
+
+fn strip_currency_suffix(text: &str) -> Option<&str> {
+    let last = text.chars().next_back()?;
+    if CURRENCY_SYMBOLS.contains(&last) {
+        return Some(&text[..text.len() - last.len_utf8()]);
+    }
+    let start = text.len().checked_sub(3)?;
+    let code = text.get(start..)?;
+    is_currency_code(code).then(|| &text[..start])
+}
+
+/// ISO 4217 code of a currency cheques can be issued in
+fn is_currency_code(code: &str) -> bool {
+    code.chars().all(|c| c.is_ascii_alphabetic()) && find_currency(code).is_some()
+}
+
+/// Work out the decimal and grouping characters of a single value
+fn detect_separators(number: &str) -> (char, Option<char>) {
+    let last_dot = number.rfind('.');
+    let last_comma = number.rfind(',');
+    match (last_dot, last_comma) {
+        (Some(dot), Some(comma)) if dot > comma => ('.', Some(',')),
+        (Some(_), Some(_)) => (',', Some('.')),
+        (Some(_), None) => lone_separator(number, '.'),
+        (None, Some(_)) => lone_separator(number, ','),
+        (None, None) => ('.', None),
+    }
+}
+
+/// A separator that appears more than once, or once ahead of exactly three digits
+/// after a short non-zero integer part, is grouping; otherwise it is the decimal point
+fn lone_separator(number: &str, separator: char) -> (char, Option<char>) {
+    let other = if separator == '.' { ',' } else { '.' };
+    let occurrences = number.matches(separator).count();
+    let (integer_part, fraction) = number.split_once(separator).unwrap_or((number, ""));
+    let grouped = occurrences > 1
+        || (fraction.len() == 3
+            && (1..=3).contains(&integer_part.len())
+            && !integer_part.starts_with('0'));
+    if grouped {
+        (other, Some(separator))
+    } else {
+        (separator, None)
+    }
+}
+
+/// Remove digit grouping, accepting thousands ("1,234,567") and lakh ("12,34,567") styles
+fn ungroup(integer_part: &str, group: char) -> Option<String> {
+    let groups: Vec<&str> = integer_part.split(group).collect();
+    let (first, rest) = groups.split_first()?;
+    let (last, middle) = rest.split_last()?;
+    let valid = (1..=3).contains(&first.len())
+        && last.len() == 3
+        && middle.iter().all(|g| g.len() == 2 || g.len() == 3);
+    valid.then(|| groups.concat())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<String, String> {
+        parse_amount_text(text, DecimalSeparator::Auto).map(|amount| amount.to_string())
+    }
+
+    #[test]
+    fn reads_locale_formats() {
+        assert_eq!(parse("$1,234.50").unwrap(), "1234.50");
+        assert_eq!(parse("1.234,50").unwrap(), "1234.50");
+        assert_eq!(parse("1 250,00 €").unwrap(), "1250.00");
+        assert_eq!(parse("1'234.5").unwrap(), "1234.5");
+        assert_eq!(parse("12,34,567").unwrap(), "1234567");
+        assert_eq!(parse("1,234").unwrap(), "1234");
+        assert_eq!(parse("0,125").unwrap(), "0.125");
+        assert_eq!(parse(".5").unwrap(), "0.5");
+        assert_eq!(parse_amount_text("1.234", DecimalSeparator::Comma).unwrap().to_string(), "1234");
+        assert_eq!(parse_amount_text("1,5", DecimalSeparator::Point).map_err(|_| ()), Err(()));
+    }
+
+    #[test]
+    fn reads_currency_codes_and_signs() {
+        assert_eq!(parse("USD 100").unwrap(), "100");
+        assert_eq!(parse("100 eur").unwrap(), "100");
+        assert_eq!(parse("(250.00)").unwrap(), "-250.00");
+        assert_eq!(parse("-$5").unwrap(), "-5");
+        assert_eq!(parse("$(5)").unwrap(), "-5");
+        assert_eq!(parse("5-").unwrap(), "-5");
+        assert_eq!(parse("+5 GBP").unwrap(), "5");
+    }
+
+    #[test]
+    fn rejects_text_around_numbers() {
+        for text in ["Invoice 42", "12 apples", "5 CR", "XYZ 5", "$$5", "USD USD 5", "abc", "", "-", "()"] {
+            assert!(parse(text).is_err(), "{} parsed", text);
+        }
+    }
+
+    #[test]
+    fn rejects_repeated_or_conflicting_signs() {
+        for text in ["--5", "-(5)", "(-5)", "5-CR-", "-5-", "+-5", "(5)-", "1.2.3,4,5"] {
+            assert!(parse(text).is_err(), "{} parsed", text);
+        }
+    }
+
+    #[test]
+    fn keeps_spreadsheet_numbers_exact() {
+        assert_eq!(decimal_from_float(0.1 + 0.2).unwrap().to_string(), "0.3");
+        assert_eq!(decimal_from_float(1234567.89).unwrap().to_string(), "1234567.89");
+        assert!(decimal_from_float(f64::NAN).is_err());
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+use serde_json::{json, Value};
+use sqlx::{Column, Row, SqliteConnection, SqlitePool};
+
+/// Tables ad hoc analytics queries may read. Signing secrets, users, settings and
+/// staged imports are out of reach, whether named directly or through a view.
+const ANALYTICS_TABLES: &[&str] = &[
+    "amount_conversion_issues",
+    "audit_trail",
+    "cheque_analytics",
+    "cheques",
+    "documents",
+    "import_issues",
+    "processing_metrics",
+];
+
+/// Run an ad hoc analytics query on a read-only connection and return its rows as
+/// JSON objects. Only a single SELECT reading the analytics tables is accepted.
+pub async fn run_analytics_query(pool: &SqlitePool, sql_query: &str) -> Result<Vec<Value>, String> {
+    let sql_query = single_select(sql_query)?;
+    let mut conn = pool
+        .acquire()
+        .await
+        .map_err(|e| format!("Failed to open a database connection: {}", e))?;
+    check_tables_read(&mut conn, sql_query).await?;
+
+    sqlx::query("PRAGMA query_only = ON")
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| format!("Failed to make the connection read-only: {}", e))?;
+    let rows = sqlx::query(sql_query).fetch_all(&mut *conn).await;
+    if sqlx::query("PRAGMA query_only = OFF").execute(&mut *conn).await.is_err() {
+        // Never hand a read-only connection back to the pool
+        drop(conn.detach());
+    }
+    let rows = rows.map_err(|e| format!("Query execution failed: {}", e))?;
+
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let mut obj = serde_json::Map::new();
+            for (i, col) in row.columns().iter().enumerate() {
+                let value: Option<String> = row.try_get(i).ok();
+                obj.insert(col.name().to_string(), json!(value));
+            }
+            json!(obj)
+        })
+        .collect())
+}
+
+/// `sql_query` without its trailing semicolon, if it is one SELECT (or WITH ... SELECT) statement
+fn single_select(sql_query: &str) -> Result<&str, String> {
+    let sql_query = sql_query.trim();
+    let sql_query = sql_query.strip_suffix(';').unwrap_or(sql_query).trim_end();
+    if sql_query.is_empty() {
+        return Err("SQL query cannot be empty".to_string());
+    }
+    if sql_query.contains(';') {
+        return Err("Only a single statement can be run".to_string());
+    }
+    let keyword = sql_query
+        .split(|c: char| !c.is_ascii_alphabetic())
+        .next()
+        .unwrap_or_default();
+    if !keyword.eq_ignore_ascii_case("SELECT") && !keyword.eq_ignore_ascii_case("WITH") {
+        return Err("Only SELECT queries can be run".to_string());
+    }
+    Ok(sql_query)
+}
+
+/// Refuse `sql_query` unless every table its compiled program opens is an analytics table.
+/// Reading the program rather than the text also catches views, aliases and quoted names.
+async fn check_tables_read(conn: &mut SqliteConnection, sql_query: &str) -> Result<(), String> {
+    let tables: HashMap<i64, String> = sqlx::query("SELECT rootpage, tbl_name FROM sqlite_master WHERE rootpage > 0")
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| format!("Failed to read the database schema: {}", e))?
+        .iter()
+        .map(|row| (row.get::<i64, _>(0), row.get::<String, _>(1)))
+        .collect();
+    let program = sqlx::query(&format!("EXPLAIN {}", sql_query))
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| format!("Query execution failed: {}", e))?;
+
+    for step in &program {
+        let opcode: String = step.try_get("opcode").map_err(|e| e.to_string())?;
+        match opcode.as_str() {
+            "OpenRead" | "ReopenIdx" => {
+                let root_page: i64 = step.try_get("p2").map_err(|e| e.to_string())?;
+                let database: i64 = step.try_get("p3").map_err(|e| e.to_string())?;
+                // Database 0 is the main one; temporary and attached databases are never read
+                let table = tables.get(&root_page).filter(|_| database == 0);
+                match table {
+                    Some(table) if ANALYTICS_TABLES.contains(&table.to_lowercase().as_str()) => {}
+                    Some(table) => return Err(format!("Query reads {}, which is not available to analytics", table)),
+                    None => return Err("Query reads a table that is not available to analytics".to_string()),
+                }
+            }
+            "VOpen" => return Err("Query reads a virtual table, which is not available to analytics".to_string()),
+            "OpenWrite" => return Err("Only SELECT queries can be run".to_string()),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    async fn analytics_db() -> SqlitePool {
+        // One connection, so every query sees the same in-memory database
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        for statement in [
+            "CREATE TABLE cheques (id INTEGER PRIMARY KEY, amount_minor INTEGER NOT NULL)",
+            "CREATE TABLE signing_secrets (id INTEGER PRIMARY KEY, secret TEXT NOT NULL)",
+            "CREATE VIEW cheque_secrets AS SELECT c.id, s.secret FROM cheques c JOIN signing_secrets s ON s.id = c.id",
+            "INSERT INTO cheques (id, amount_minor) VALUES (1, 1050)",
+            "INSERT INTO signing_secrets (id, secret) VALUES (1, 'hunter2')",
+        ] {
+            sqlx::query(statement).execute(&pool).await.unwrap();
+        }
+        pool
+    }
+
+    #[test]
+    fn accepts_only_a_single_select() {
+        assert_eq!(single_select("  select 1;  ").unwrap(), "select 1");
+        assert_eq!(single_select("WITH t AS (SELECT 1) SELECT * FROM t").unwrap(), "WITH t AS (SELECT 1) SELECT * FROM t");
+        assert!(single_select("  ;").is_err());
+        assert!(single_select("SELECT 1; DELETE FROM cheques").is_err());
+        assert!(single_select("UPDATE cheques SET amount_minor = 0").is_err());
+        assert!(single_select("PRAGMA query_only = OFF").is_err());
+    }
+
+    #[test]
+    fn signing_secrets_cannot_be_reached() {
+        tauri::async_runtime::block_on(async {
+            let pool = analytics_db().await;
+
+            let rows = run_analytics_query(&pool, "SELECT CAST(amount_minor AS TEXT) AS amount FROM cheques").await.unwrap();
+            assert_eq!(rows, vec![json!({ "amount": "1050" })]);
+
+            for query in [
+                "SELECT secret FROM signing_secrets",
+                "select secret from \"Signing_Secrets\"",
+                "SELECT s.secret FROM cheques c, signing_secrets AS s",
+                "SELECT * FROM cheques WHERE id IN (SELECT id FROM signing_secrets)",
+                "SELECT secret FROM cheque_secrets",
+                "SELECT sql FROM sqlite_master",
+                "SELECT * FROM pragma_table_info('signing_secrets')",
+            ] {
+                let error = run_analytics_query(&pool, query).await.unwrap_err();
+                assert!(error.contains("not available to analytics"), "{}: {}", query, error);
+            }
+        });
+    }
+
+    #[test]
+    fn refuses_writes_and_leaves_the_connection_writable() {
+        tauri::async_runtime::block_on(async {
+            let pool = analytics_db().await;
+
+            for query in [
+                "DELETE FROM cheques",
+                "SELECT 1; DELETE FROM cheques",
+                "WITH t AS (SELECT 1) DELETE FROM cheques",
+                "WITH t AS (SELECT 1) INSERT INTO cheques (amount_minor) SELECT 5 FROM t",
+            ] {
+                assert!(run_analytics_query(&pool, query).await.is_err(), "{}", query);
+            }
+            let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM cheques").fetch_one(&pool).await.unwrap();
+            assert_eq!(count, 1);
+
+            run_analytics_query(&pool, "SELECT amount_minor FROM cheques").await.unwrap();
+            sqlx::query("INSERT INTO cheques (amount_minor) VALUES (2000)").execute(&pool).await.unwrap();
+        });
+    }
+}
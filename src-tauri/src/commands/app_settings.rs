@@ -0,0 +1,40 @@
+use serde::{de::DeserializeOwned, Serialize};
+use sqlx::SqliteConnection;
+
+/// Read a JSON setting, returning `None` when it has never been stored
+pub async fn get_setting<T: DeserializeOwned>(
+    conn: &mut SqliteConnection,
+    key: &str,
+) -> Result<Option<T>, String> {
+    let value: Option<String> = sqlx::query_scalar("SELECT value FROM app_settings WHERE key = ?")
+        .bind(key)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| format!("Failed to read setting '{}': {}", key, e))?;
+
+    value
+        .map(|v| serde_json::from_str(&v).map_err(|e| format!("Setting '{}' is invalid: {}", key, e)))
+        .transpose()
+}
+
+/// Store a setting as JSON, replacing any previous value
+pub async fn set_setting<T: Serialize>(
+    conn: &mut SqliteConnection,
+    key: &str,
+    value: &T,
+) -> Result<(), String> {
+    let json = serde_json::to_string(value)
+        .map_err(|e| format!("Failed to serialize setting '{}': {}", key, e))?;
+
+    sqlx::query(
+        "INSERT INTO app_settings (key, value) VALUES (?, ?)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value"
+    )
+    .bind(key)
+    .bind(json)
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| format!("Failed to save setting '{}': {}", key, e))?;
+
+    Ok(())
+}
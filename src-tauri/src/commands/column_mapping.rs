@@ -0,0 +1,329 @@
+use std::collections::HashMap;
+use calamine::{Data, Range};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use crate::database::models::MappingProfile;
+use super::amount_parser::DecimalSeparator;
+
+/// Cheque fields that can be read from a spreadsheet column
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChequeField {
+    ChequeNumber,
+    Amount,
+    ClientName,
+    AccountNumber,
+    IssueDate,
+    Memo,
+    Remarks,
+    AddressLine1,
+    AddressLine2,
+    City,
+    Region,
+    PostalCode,
+    Country,
+    DeliveryMethod,
+    Currency,
+}
+
+impl ChequeField {
+    pub const ALL: [ChequeField; 15] = [
+        ChequeField::ChequeNumber,
+        ChequeField::Amount,
+        ChequeField::ClientName,
+        ChequeField::AccountNumber,
+        ChequeField::IssueDate,
+        ChequeField::Memo,
+        ChequeField::Remarks,
+        ChequeField::AddressLine1,
+        ChequeField::AddressLine2,
+        ChequeField::City,
+        ChequeField::Region,
+        ChequeField::PostalCode,
+        ChequeField::Country,
+        ChequeField::DeliveryMethod,
+        ChequeField::Currency,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChequeField::ChequeNumber => "cheque_number",
+            ChequeField::Amount => "amount",
+            ChequeField::ClientName => "client_name",
+            ChequeField::AccountNumber => "account_number",
+            ChequeField::IssueDate => "issue_date",
+            ChequeField::Memo => "memo",
+            ChequeField::Remarks => "remarks",
+            ChequeField::AddressLine1 => "address_line1",
+            ChequeField::AddressLine2 => "address_line2",
+            ChequeField::City => "city",
+            ChequeField::Region => "region",
+            ChequeField::PostalCode => "postal_code",
+            ChequeField::Country => "country",
+            ChequeField::DeliveryMethod => "delivery_method",
+            ChequeField::Currency => "currency",
+        }
+    }
+
+    /// Whether a sheet must contain this field for the import to work at all
+    pub fn is_required(&self) -> bool {
+        matches!(self, ChequeField::ChequeNumber | ChequeField::Amount | ChequeField::ClientName)
+    }
+
+    /// Column position used by the original fixed layout
+    fn legacy_index(&self) -> Option<u32> {
+        match self {
+            ChequeField::ChequeNumber => Some(0),
+            ChequeField::Amount => Some(1),
+            ChequeField::ClientName => Some(2),
+            _ => None,
+        }
+    }
+
+    /// Normalised header spellings recognised during auto-detection
+    fn header_aliases(&self) -> &'static [&'static str] {
+        match self {
+            ChequeField::ChequeNumber => &[
+                "chequenumber", "chequeno", "chequenum", "cheque",
+                "checknumber", "checkno", "checknum", "check",
+            ],
+            ChequeField::Amount => &[
+                "amount", "amt", "chequeamount", "checkamount", "paymentamount", "value", "total",
+            ],
+            ChequeField::ClientName => &[
+                "clientname", "client", "payee", "payeename", "beneficiary", "name", "vendor", "supplier",
+            ],
+            ChequeField::AccountNumber => &[
+                "accountnumber", "accountno", "account", "bankaccount", "payingaccount",
+            ],
+            ChequeField::IssueDate => &[
+                "issuedate", "chequedate", "checkdate", "date", "paymentdate", "dateofissue",
+            ],
+            ChequeField::Memo => &[
+                "memo", "description", "narration", "particulars", "purpose", "paymentfor",
+            ],
+            ChequeField::Remarks => &[
+                "remarks", "remark", "notes", "note", "reference", "ref", "internalreference", "comments",
+            ],
+            ChequeField::AddressLine1 => &[
+                "addressline1", "address1", "address", "street", "streetaddress", "payeeaddress",
+            ],
+            ChequeField::AddressLine2 => &["addressline2", "address2", "suite", "unit"],
+            ChequeField::City => &["city", "town", "locality"],
+            ChequeField::Region => &["region", "state", "province", "county", "stateprovince"],
+            ChequeField::PostalCode => &["postalcode", "postcode", "zip", "zipcode", "pincode"],
+            ChequeField::Country => &["country", "countrycode"],
+            ChequeField::DeliveryMethod => &["deliverymethod", "delivery", "deliverytype", "dispatch"],
+            ChequeField::Currency => &["currency", "currencycode", "ccy", "curr"],
+        }
+    }
+}
+
+/// Location of a field in the sheet, either by header text or column letter
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnRef {
+    Header(String),
+    Column(String),
+}
+
+/// Import layout used by both the preview and the persisted upload
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColumnMapping {
+    /// Sheet to import when none is chosen at upload time; defaults to the first sheet
+    pub sheet_name: Option<String>,
+    /// 1-based row holding the column headers; data starts on the next row
+    pub header_row: u32,
+    pub columns: HashMap<ChequeField, ColumnRef>,
+    /// Decimal point used by amounts stored as text
+    pub decimal_separator: DecimalSeparator,
+    /// Zero-pad cheque numbers read from numeric cells to this many digits
+    pub cheque_number_digits: Option<u32>,
+}
+
+impl Default for ColumnMapping {
+    fn default() -> Self {
+        ColumnMapping {
+            sheet_name: None,
+            header_row: 1,
+            columns: HashMap::new(),
+            decimal_separator: DecimalSeparator::Auto,
+            cheque_number_digits: None,
+        }
+    }
+}
+
+/// Mapping resolved against a concrete sheet, with absolute 0-based positions
+#[derive(Debug, Clone)]
+pub struct ResolvedColumns {
+    pub header_row: u32,
+    pub columns: HashMap<ChequeField, u32>,
+}
+
+impl ResolvedColumns {
+    /// Cell for `field` in the row at absolute index `row`
+    pub fn cell<'a>(&self, range: &'a Range<Data>, row: u32, field: ChequeField) -> Option<&'a Data> {
+        let col = *self.columns.get(&field)?;
+        range.get_value((row, col))
+    }
+
+    /// Absolute indexes of the data rows that follow the header row
+    pub fn data_rows(&self, range: &Range<Data>) -> std::ops::Range<u32> {
+        let end = range.end().map(|(row, _)| row + 1).unwrap_or(0);
+        (self.header_row + 1).min(end)..end
+    }
+}
+
+impl ColumnMapping {
+    pub fn from_profile(profile: &MappingProfile) -> Result<Self, String> {
+        let columns: HashMap<ChequeField, ColumnRef> = serde_json::from_str(&profile.column_map)
+            .map_err(|e| format!("Mapping profile '{}' has an invalid column map: {}", profile.name, e))?;
+
+        if profile.header_row < 1 {
+            return Err(format!("Mapping profile '{}' has an invalid header row", profile.name));
+        }
+
+        let decimal_separator = profile
+            .decimal_separator
+            .parse()
+            .map_err(|e| format!("Mapping profile '{}': {}", profile.name, e))?;
+
+        Ok(ColumnMapping {
+            sheet_name: profile.sheet_name.clone(),
+            header_row: profile.header_row as u32,
+            columns,
+            decimal_separator,
+            cheque_number_digits: profile.cheque_number_digits.map(|digits| digits as u32),
+        })
+    }
+
+    /// Resolve every field to a column index. Fields absent from the profile
+    /// are detected from the header text, then fall back to the legacy layout.
+    pub fn resolve(&self, range: &Range<Data>, sheet_name: &str) -> Result<ResolvedColumns, String> {
+        let header_row = self.header_row.saturating_sub(1);
+        let headers = header_cells(range, header_row);
+        let mut columns: HashMap<ChequeField, u32> = HashMap::new();
+
+        for (field, column_ref) in &self.columns {
+            let index = match column_ref {
+                ColumnRef::Column(letters) => column_letters_to_index(letters)
+                    .ok_or_else(|| format!("Invalid column letter '{}' for {}", letters, field.as_str()))?,
+                ColumnRef::Header(name) => {
+                    let wanted = normalize_header(name);
+                    headers
+                        .iter()
+                        .find(|(_, text)| *text == wanted)
+                        .map(|(col, _)| *col)
+                        .ok_or_else(|| format!(
+                            "Header '{}' for {} not found in row {} of sheet '{}'",
+                            name, field.as_str(), self.header_row, sheet_name
+                        ))?
+                }
+            };
+            columns.insert(*field, index);
+        }
+
+        for field in ChequeField::ALL {
+            if columns.contains_key(&field) {
+                continue;
+            }
+            let detected = headers
+                .iter()
+                .find(|(col, text)| {
+                    field.header_aliases().contains(&text.as_str()) && !columns.values().any(|c| c == col)
+                })
+                .map(|(col, _)| *col);
+            if let Some(col) = detected {
+                columns.insert(field, col);
+            }
+        }
+
+        for field in ChequeField::ALL {
+            if columns.contains_key(&field) {
+                continue;
+            }
+            if let Some(col) = field.legacy_index() {
+                if !columns.values().any(|c| *c == col) {
+                    columns.insert(field, col);
+                }
+            }
+        }
+
+        if let Some(missing) = ChequeField::ALL
+            .iter()
+            .find(|f| f.is_required() && !columns.contains_key(f))
+        {
+            return Err(format!(
+                "Could not locate a column for {} in sheet '{}'",
+                missing.as_str(), sheet_name
+            ));
+        }
+
+        Ok(ResolvedColumns { header_row, columns })
+    }
+}
+
+/// Load the mapping for `profile_id`, or the auto-detecting default when none is given
+pub async fn load_mapping(pool: &SqlitePool, profile_id: Option<i64>) -> Result<ColumnMapping, String> {
+    let Some(id) = profile_id else {
+        return Ok(ColumnMapping::default());
+    };
+
+    let profile = sqlx::query_as::<_, MappingProfile>(
+        "SELECT id, name, sheet_name, header_row, column_map, decimal_separator, cheque_number_digits,
+                created_at, updated_at
+         FROM mapping_profiles WHERE id = ?"
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to load mapping profile: {}", e))?
+    .ok_or_else(|| format!("Mapping profile with ID {} not found", id))?;
+
+    ColumnMapping::from_profile(&profile)
+}
+
+/// Convert a column letter such as "A" or "AB" to a 0-based index
+pub fn column_letters_to_index(letters: &str) -> Option<u32> {
+    let letters = letters.trim();
+    if letters.is_empty() || letters.len() > 3 {
+        return None;
+    }
+    letters.chars().try_fold(0u32, |acc, c| {
+        c.is_ascii_alphabetic()
+            .then(|| acc * 26 + (c.to_ascii_uppercase() as u32 - 'A' as u32 + 1))
+    })
+    .map(|n| n - 1)
+}
+
+/// Convert a 0-based column index to its letter form, e.g. 27 -> "AB"
+pub fn column_index_to_letters(index: u32) -> String {
+    let mut letters = Vec::new();
+    let mut n = index + 1;
+    while n > 0 {
+        let rem = (n - 1) % 26;
+        letters.push((b'A' + rem as u8) as char);
+        n = (n - 1) / 26;
+    }
+    letters.iter().rev().collect()
+}
+
+fn normalize_header(text: &str) -> String {
+    text.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+fn header_cells(range: &Range<Data>, row: u32) -> Vec<(u32, String)> {
+    let (Some((_, first_col)), Some((_, last_col))) = (range.start(), range.end()) else {
+        return Vec::new();
+    };
+    (first_col..=last_col)
+        .filter_map(|col| match range.get_value((row, col)) {
+            Some(Data::String(s)) => Some((col, normalize_header(s))),
+            _ => None,
+        })
+        .filter(|(_, text)| !text.is_empty())
+        .collect()
+}
@@ -0,0 +1,211 @@
+use calamine::{Data, Range};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use super::amount_parser::{decimal_from_float, parse_amount_text, DecimalSeparator};
+use super::column_mapping::{column_letters_to_index, ChequeField, ResolvedColumns};
+use super::import_parser::ParsedCheque;
+use super::spreadsheet::read_cell_reference;
+
+/// Where the expected total amount and cheque count of a batch come from.
+/// Totals cover the cheques actually imported, so a partial import with
+/// rejected rows fails the check.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "source", rename_all = "snake_case", rename_all_fields = "camelCase")]
+pub enum ControlTotals {
+    /// The last non-blank row of each sheet is a trailer holding that sheet's
+    /// totals rather than a cheque. Columns are letters; the amount defaults to
+    /// the mapped amount column and the count is only checked when given.
+    TrailerRow {
+        amount_column: Option<String>,
+        count_column: Option<String>,
+    },
+    /// Workbook defined names or cell references such as `Summary!B2`
+    NamedCells {
+        amount_cell: String,
+        count_cell: Option<String>,
+    },
+    /// Totals typed in by the user at upload time
+    Entered {
+        total_amount: Decimal,
+        row_count: Option<u32>,
+    },
+}
+
+/// Totals a file or user declares for a batch, or for one sheet of it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeclaredTotals {
+    /// Where the totals were read, such as "trailer row 12 of 'Sheet1'"
+    pub source: String,
+    /// Sheet the totals cover; the whole batch when absent
+    pub sheet_name: Option<String>,
+    pub total_amount: Decimal,
+    pub row_count: Option<u32>,
+}
+
+/// Declared totals compared with the cheques read from the file
+#[derive(Debug, Clone, Serialize)]
+pub struct ControlTotalsCheck {
+    #[serde(flatten)]
+    pub declared: DeclaredTotals,
+    pub actual_amount: Decimal,
+    pub actual_count: u32,
+    pub matches: bool,
+}
+
+/// Index of the last row in `rows` holding any value, which a trailer-row batch treats as its trailer
+pub fn find_trailer_row(range: &Range<Data>, rows: std::ops::Range<u32>) -> Option<u32> {
+    let (_, start_col) = range.start()?;
+    let (_, end_col) = range.end()?;
+    rows.rev().find(|row| {
+        (start_col..=end_col).any(|col| match range.get_value((*row, col)) {
+            None | Some(Data::Empty) => false,
+            Some(Data::String(s)) => !s.trim().is_empty(),
+            Some(_) => true,
+        })
+    })
+}
+
+/// Read the totals from the trailer row at absolute index `row`
+pub fn read_trailer_totals(
+    range: &Range<Data>,
+    columns: &ResolvedColumns,
+    row: u32,
+    sheet_name: &str,
+    amount_column: Option<&str>,
+    count_column: Option<&str>,
+    separator: DecimalSeparator,
+) -> Result<DeclaredTotals, String> {
+    let source = format!("trailer row {} of '{}'", row + 1, sheet_name);
+    let column = |letters: &str| {
+        column_letters_to_index(letters).ok_or_else(|| format!("Invalid trailer column letter '{}'", letters))
+    };
+    let cell = |col: u32| range.get_value((row, col)).unwrap_or(&Data::Empty);
+
+    let amount_col = match amount_column {
+        Some(letters) => column(letters)?,
+        None => *columns
+            .columns
+            .get(&ChequeField::Amount)
+            .ok_or_else(|| format!("Sheet '{}' has no amount column for the trailer total", sheet_name))?,
+    };
+    let total_amount = parse_total_amount(cell(amount_col), separator).map_err(|e| format!("{}: {}", source, e))?;
+    let row_count = count_column
+        .map(|letters| column(letters).and_then(|col| parse_row_count(cell(col))))
+        .transpose()
+        .map_err(|e| format!("{}: {}", source, e))?;
+
+    Ok(DeclaredTotals { source, sheet_name: Some(sheet_name.to_string()), total_amount, row_count })
+}
+
+/// Read the totals from named cells of the workbook
+pub fn read_named_totals(
+    data: &[u8],
+    file_name: &str,
+    amount_cell: &str,
+    count_cell: Option<&str>,
+    separator: DecimalSeparator,
+) -> Result<DeclaredTotals, String> {
+    let total_amount = parse_total_amount(&read_cell_reference(data, file_name, amount_cell)?, separator)
+        .map_err(|e| format!("Control total '{}': {}", amount_cell, e))?;
+    let row_count = count_cell
+        .map(|reference| {
+            read_cell_reference(data, file_name, reference).and_then(|cell| {
+                parse_row_count(&cell).map_err(|e| format!("Control count '{}': {}", reference, e))
+            })
+        })
+        .transpose()?;
+
+    let source = match count_cell {
+        Some(count_cell) => format!("cells {} and {}", amount_cell, count_cell),
+        None => format!("cell {}", amount_cell),
+    };
+    Ok(DeclaredTotals { source, sheet_name: None, total_amount, row_count })
+}
+
+pub fn entered_totals(total_amount: Decimal, row_count: Option<u32>) -> DeclaredTotals {
+    DeclaredTotals { source: "entered totals".to_string(), sheet_name: None, total_amount, row_count }
+}
+
+/// Compare each set of declared totals with the cheques it covers
+pub fn check_control_totals(declared: &[DeclaredTotals], cheques: &[ParsedCheque]) -> Vec<ControlTotalsCheck> {
+    declared
+        .iter()
+        .map(|totals| {
+            let covered: Vec<&ParsedCheque> = cheques
+                .iter()
+                .filter(|cheque| totals.sheet_name.as_ref().is_none_or(|sheet| *sheet == cheque.sheet_name))
+                .collect();
+            let actual_amount: Decimal = covered.iter().map(|cheque| cheque.amount).sum();
+            let actual_count = covered.len() as u32;
+            let matches = actual_amount == totals.total_amount
+                && totals.row_count.is_none_or(|count| count == actual_count);
+            ControlTotalsCheck {
+                declared: totals.clone(),
+                actual_amount,
+                actual_count,
+                matches,
+            }
+        })
+        .collect()
+}
+
+/// Rejection message listing every failed check, if any
+pub fn control_totals_error(file_name: &str, checks: &[ControlTotalsCheck]) -> Option<String> {
+    let failed: Vec<String> = checks
+        .iter()
+        .filter(|check| !check.matches)
+        .map(|check| {
+            let expected_count = check
+                .declared
+                .row_count
+                .map(|count| format!("{} cheque(s) ", count))
+                .unwrap_or_default();
+            format!(
+                "{} declares {}totalling {} but {} cheque(s) totalling {} were read",
+                check.declared.source,
+                expected_count,
+                with_cents(check.declared.total_amount),
+                check.actual_count,
+                with_cents(check.actual_amount)
+            )
+        })
+        .collect();
+    (!failed.is_empty()).then(|| {
+        format!(
+            "File '{}' was not imported: control totals do not match. The file may be truncated or edited.\n{}",
+            file_name,
+            failed.join("\n")
+        )
+    })
+}
+
+fn parse_total_amount(cell: &Data, separator: DecimalSeparator) -> Result<Decimal, String> {
+    match cell {
+        Data::Float(f) => decimal_from_float(*f),
+        Data::Int(i) => Ok(Decimal::from(*i)),
+        Data::String(s) if !s.trim().is_empty() => parse_amount_text(s, separator),
+        Data::Empty | Data::String(_) => Err("total amount is empty".to_string()),
+        other => Err(format!("'{}' is not a total amount", other)),
+    }
+}
+
+fn parse_row_count(cell: &Data) -> Result<u32, String> {
+    let count = match cell {
+        Data::Int(i) => Some(*i),
+        Data::Float(f) if f.fract() == 0.0 => Some(*f as i64),
+        Data::String(s) => s.trim().parse::<i64>().ok(),
+        _ => None,
+    };
+    count
+        .and_then(|count| u32::try_from(count).ok())
+        .ok_or_else(|| format!("'{}' is not a row count", cell))
+}
+
+/// `amount` shown with at least two decimal places, keeping any further ones
+fn with_cents(amount: Decimal) -> Decimal {
+    let mut shown = amount;
+    if shown.scale() < 2 {
+        shown.rescale(2);
+    }
+    shown
+}
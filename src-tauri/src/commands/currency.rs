@@ -0,0 +1,199 @@
+use rust_decimal::Decimal;
+use serde::Serialize;
+use super::number_words::{Gender, Language, UnitName};
+
+/// Currency of cheques that do not name one, and of all cheques issued before
+/// currencies were recorded
+pub const DEFAULT_CURRENCY: &str = "USD";
+
+/// Names of a currency's units in one language
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct CurrencyUnits {
+    pub language: Language,
+    pub major_unit: UnitName,
+    /// Absent for currencies without a minor unit in use, such as the yen
+    pub minor_unit: Option<UnitName>,
+}
+
+/// Currency cheques can be issued in
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Currency {
+    /// ISO 4217 code
+    pub code: &'static str,
+    pub name: &'static str,
+    /// Unit names by language, English first
+    pub units: &'static [CurrencyUnits],
+    /// Decimal places of the minor unit: 2 for cents, 0 when there is none
+    pub minor_digits: u32,
+}
+
+const fn masculine(singular: &'static str, plural: &'static str) -> UnitName {
+    UnitName { singular, plural, gender: Gender::Masculine }
+}
+
+const fn feminine(singular: &'static str, plural: &'static str) -> UnitName {
+    UnitName { singular, plural, gender: Gender::Feminine }
+}
+
+const fn units(language: Language, major_unit: UnitName, minor_unit: Option<UnitName>) -> CurrencyUnits {
+    CurrencyUnits { language, major_unit, minor_unit }
+}
+
+const DOLLAR_UNITS: &[CurrencyUnits] = &[
+    units(Language::English, masculine("Dollar", "Dollars"), Some(masculine("Cent", "Cents"))),
+    units(Language::French, masculine("dollar", "dollars"), Some(masculine("cent", "cents"))),
+    units(Language::Spanish, masculine("dólar", "dólares"), Some(masculine("centavo", "centavos"))),
+];
+
+pub static CURRENCIES: &[Currency] = &[
+    Currency { code: "USD", name: "US Dollar", units: DOLLAR_UNITS, minor_digits: 2 },
+    Currency { code: "CAD", name: "Canadian Dollar", units: DOLLAR_UNITS, minor_digits: 2 },
+    Currency {
+        code: "EUR",
+        name: "Euro",
+        units: &[
+            units(Language::English, masculine("Euro", "Euros"), Some(masculine("Cent", "Cents"))),
+            units(Language::French, masculine("euro", "euros"), Some(masculine("centime", "centimes"))),
+            units(Language::Spanish, masculine("euro", "euros"), Some(masculine("céntimo", "céntimos"))),
+        ],
+        minor_digits: 2,
+    },
+    Currency {
+        code: "GBP",
+        name: "Pound Sterling",
+        units: &[
+            units(Language::English, masculine("Pound", "Pounds"), Some(masculine("Penny", "Pence"))),
+            units(Language::French, feminine("livre", "livres"), Some(masculine("penny", "pence"))),
+            units(Language::Spanish, feminine("libra", "libras"), Some(masculine("penique", "peniques"))),
+        ],
+        minor_digits: 2,
+    },
+    Currency { code: "TTD", name: "Trinidad and Tobago Dollar", units: DOLLAR_UNITS, minor_digits: 2 },
+    Currency {
+        code: "JPY",
+        name: "Japanese Yen",
+        units: &[
+            units(Language::English, masculine("Yen", "Yen"), None),
+            units(Language::French, masculine("yen", "yens"), None),
+            units(Language::Spanish, masculine("yen", "yenes"), None),
+        ],
+        minor_digits: 0,
+    },
+];
+
+/// Look up a currency by its code, ignoring case and surrounding spaces
+pub fn find_currency(code: &str) -> Option<&'static Currency> {
+    let code = code.trim();
+    CURRENCIES.iter().find(|currency| currency.code.eq_ignore_ascii_case(code))
+}
+
+/// Registry entry for `DEFAULT_CURRENCY`
+pub fn default_currency() -> &'static Currency {
+    find_currency(DEFAULT_CURRENCY).expect("the default currency is registered")
+}
+
+/// Amount of a stored cheque from its minor units and currency code
+pub fn stored_amount(amount_minor: i64, currency: &str) -> Decimal {
+    find_currency(currency).unwrap_or_else(default_currency).from_minor_units(amount_minor)
+}
+
+/// Like `find_currency`, with an error naming the supported codes
+pub fn parse_currency(code: &str) -> Result<&'static Currency, String> {
+    find_currency(code).ok_or_else(|| {
+        let codes: Vec<&str> = CURRENCIES.iter().map(|currency| currency.code).collect();
+        format!("Unknown currency '{}'; use one of {}", code.trim(), codes.join(", "))
+    })
+}
+
+impl Currency {
+    /// Unit names in `language`, falling back to English
+    pub fn units(&self, language: Language) -> &'static CurrencyUnits {
+        self.units.iter().find(|units| units.language == language).unwrap_or(&self.units[0])
+    }
+
+    /// `amount` as a whole number of minor units, the way amounts are stored,
+    /// rejecting amounts with more decimal places than the currency allows
+    pub fn to_minor_units(&self, amount: Decimal) -> Result<i64, String> {
+        if amount.normalize().scale() > self.minor_digits {
+            return Err(match self.minor_digits {
+                0 => format!("{} amounts cannot have decimal places", self.code),
+                digits => format!("{} amounts have at most {} decimal places", self.code, digits),
+            });
+        }
+        let mut scaled = amount;
+        scaled.rescale(self.minor_digits);
+        i64::try_from(scaled.mantissa()).map_err(|_| format!("Amount {} is too large", amount))
+    }
+
+    /// Amount of `minor` minor units, with the currency's decimal places
+    pub fn from_minor_units(&self, minor: i64) -> Decimal {
+        Decimal::new(minor, self.minor_digits)
+    }
+
+    /// Split a non-negative `amount` into whole major units and minor units
+    pub fn split_amount(&self, amount: Decimal) -> Result<(u64, u64), String> {
+        let minor_total = u64::try_from(self.to_minor_units(amount)?)
+            .map_err(|_| format!("Amount {} is negative", amount))?;
+        let scale = 10u64.pow(self.minor_digits);
+        Ok((minor_total / scale, minor_total % scale))
+    }
+}
+
+/// Currencies cheques can be issued in, for currency pickers
+#[tauri::command]
+pub fn get_currencies() -> Result<String, String> {
+    serde_json::to_string(CURRENCIES).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn dec(text: &str) -> Decimal {
+        Decimal::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn converts_to_minor_units_by_currency() {
+        let usd = find_currency("usd").unwrap();
+        let jpy = find_currency(" JPY ").unwrap();
+        assert_eq!(usd.to_minor_units(dec("12.5")).unwrap(), 1250);
+        assert_eq!(usd.to_minor_units(dec("12.500")).unwrap(), 1250);
+        assert_eq!(usd.to_minor_units(dec("0.01")).unwrap(), 1);
+        assert_eq!(usd.to_minor_units(dec("-3.20")).unwrap(), -320);
+        assert_eq!(jpy.to_minor_units(dec("25000")).unwrap(), 25000);
+        assert_eq!(jpy.to_minor_units(dec("25000.00")).unwrap(), 25000);
+    }
+
+    #[test]
+    fn rejects_amounts_finer_than_the_minor_unit() {
+        let usd = find_currency("USD").unwrap();
+        let jpy = find_currency("JPY").unwrap();
+        assert_eq!(usd.to_minor_units(dec("10.005")).unwrap_err(), "USD amounts have at most 2 decimal places");
+        assert_eq!(jpy.to_minor_units(dec("1.5")).unwrap_err(), "JPY amounts cannot have decimal places");
+        assert_eq!(usd.to_minor_units(Decimal::MAX).unwrap_err(), format!("Amount {} is too large", Decimal::MAX));
+    }
+
+    #[test]
+    fn reads_stored_minor_units() {
+        assert_eq!(stored_amount(1250, "USD").to_string(), "12.50");
+        assert_eq!(stored_amount(1250, "JPY").to_string(), "1250");
+        // Unknown codes are read as the default currency
+        assert_eq!(stored_amount(1250, "XYZ").to_string(), "12.50");
+        for currency in CURRENCIES {
+            let amount = currency.from_minor_units(123_456);
+            assert_eq!(currency.to_minor_units(amount).unwrap(), 123_456, "{}", currency.code);
+        }
+    }
+
+    #[test]
+    fn splits_amounts_into_major_and_minor_units() {
+        let gbp = find_currency("GBP").unwrap();
+        let jpy = find_currency("JPY").unwrap();
+        assert_eq!(gbp.split_amount(dec("1234.05")).unwrap(), (1234, 5));
+        assert_eq!(jpy.split_amount(dec("1234")).unwrap(), (1234, 0));
+        assert_eq!(gbp.split_amount(dec("-1")).unwrap_err(), "Amount -1 is negative");
+        assert_eq!(parse_currency("XYZ").unwrap_err(), "Unknown currency 'XYZ'; use one of USD, CAD, EUR, GBP, TTD, JPY");
+    }
+}
@@ -0,0 +1,105 @@
+use std::borrow::Cow;
+use calamine::{Cell, Data, Range};
+use encoding_rs::{Encoding, WINDOWS_1252};
+
+const CANDIDATE_DELIMITERS: [u8; 4] = [b',', b'\t', b';', b'|'];
+const SNIFF_LINES: usize = 20;
+
+/// Decode text as UTF-8 (or UTF-16 when a BOM says so), falling back to Windows-1252
+pub fn decode_text(data: &[u8]) -> Cow<'_, str> {
+    if let Some((encoding, bom_length)) = Encoding::for_bom(data) {
+        let (text, _) = encoding.decode_without_bom_handling(&data[bom_length..]);
+        return text;
+    }
+    match std::str::from_utf8(data) {
+        Ok(text) => Cow::Borrowed(text),
+        Err(_) => WINDOWS_1252.decode_without_bom_handling(data).0,
+    }
+}
+
+/// Pick the delimiter that splits the first lines into the most consistent
+/// number of fields, ignoring delimiters inside quoted values
+pub fn sniff_delimiter(text: &str, file_name: &str) -> u8 {
+    let lines: Vec<&str> = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .take(SNIFF_LINES)
+        .collect();
+
+    let mut best: Option<(u8, usize, usize)> = None;
+    for delimiter in CANDIDATE_DELIMITERS {
+        let counts: Vec<usize> = lines
+            .iter()
+            .map(|line| count_unquoted(line, delimiter))
+            .collect();
+        let Some(&first) = counts.first() else { continue };
+        if first == 0 {
+            continue;
+        }
+        let consistent = counts.iter().filter(|&&c| c == first).count();
+        let better = match best {
+            None => true,
+            Some((_, best_consistent, best_count)) => {
+                consistent > best_consistent || (consistent == best_consistent && first > best_count)
+            }
+        };
+        if better {
+            best = Some((delimiter, consistent, first));
+        }
+    }
+
+    match best {
+        Some((delimiter, _, _)) => delimiter,
+        None if file_name.to_ascii_lowercase().ends_with(".tsv") => b'\t',
+        None => b',',
+    }
+}
+
+/// Parse CSV/TSV bytes into a sheet-shaped range so it can share the workbook import path
+pub fn read_delimited(data: &[u8], file_name: &str) -> Result<Range<Data>, String> {
+    let text = decode_text(data);
+    let delimiter = sniff_delimiter(&text, file_name);
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(text.as_bytes());
+
+    let mut cells: Vec<Cell<Data>> = Vec::new();
+    for (row, record) in reader.records().enumerate() {
+        let record = record.map_err(|e| format!("Failed to read row {} of '{}': {}", row + 1, file_name, e))?;
+        for (col, field) in record.iter().enumerate() {
+            let value = text_to_cell(field);
+            if value != Data::Empty {
+                cells.push(Cell::new((row as u32, col as u32), value));
+            }
+        }
+    }
+
+    Ok(Range::from_sparse(cells))
+}
+
+fn count_unquoted(line: &str, delimiter: u8) -> usize {
+    let mut in_quotes = false;
+    let mut count = 0;
+    for byte in line.bytes() {
+        if byte == b'"' {
+            in_quotes = !in_quotes;
+        } else if byte == delimiter && !in_quotes {
+            count += 1;
+        }
+    }
+    count
+}
+
+/// Keep each field as text; the parser for the field it is mapped to decides
+/// whether it is an amount, a date or an identifier, so cheque numbers such as
+/// "0012" or "12E3" are never turned into numbers
+fn text_to_cell(field: &str) -> Data {
+    let trimmed = field.trim();
+    if trimmed.is_empty() {
+        return Data::Empty;
+    }
+    Data::String(trimmed.to_string())
+}
@@ -0,0 +1,67 @@
+use chrono::NaiveDateTime;
+use sha2::{Digest, Sha256};
+use sqlx::{SqliteConnection, SqlitePool};
+
+/// Earlier upload with identical content
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct DuplicateDocument {
+    pub id: i64,
+    pub file_name: String,
+    pub created_at: NaiveDateTime,
+}
+
+/// Lowercase hex SHA-256 of an uploaded file
+pub fn content_hash(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// Find the oldest document whose content hash matches `hash`
+pub async fn find_duplicate_document(
+    conn: &mut SqliteConnection,
+    hash: &str,
+) -> Result<Option<DuplicateDocument>, String> {
+    sqlx::query_as::<_, DuplicateDocument>(
+        "SELECT id, file_name, created_at FROM documents WHERE content_hash = ? ORDER BY created_at ASC LIMIT 1"
+    )
+    .bind(hash)
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|e| format!("Failed to check for duplicate documents: {}", e))
+}
+
+/// Refusal of a file identical to `earlier`, which an administrator can override
+pub fn duplicate_file_error(file_name: &str, earlier: &DuplicateDocument) -> String {
+    format!(
+        "File '{}' is identical to '{}' (document {}) uploaded on {}. An administrator can override this check.",
+        file_name,
+        earlier.file_name,
+        earlier.id,
+        earlier.created_at.format("%Y-%m-%d %H:%M")
+    )
+}
+
+/// Hash documents uploaded before content hashes were recorded. Returns the number updated.
+pub async fn backfill_document_hashes(pool: &SqlitePool) -> Result<u64, String> {
+    let ids: Vec<i64> = sqlx::query_scalar("SELECT id FROM documents WHERE content_hash IS NULL")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to list unhashed documents: {}", e))?;
+
+    let mut updated = 0;
+    for id in ids {
+        let data: Vec<u8> = sqlx::query_scalar("SELECT file_data FROM documents WHERE id = ?")
+            .bind(id)
+            .fetch_one(pool)
+            .await
+            .map_err(|e| format!("Failed to read document {}: {}", id, e))?;
+
+        updated += sqlx::query("UPDATE documents SET content_hash = ? WHERE id = ?")
+            .bind(content_hash(&data))
+            .bind(id)
+            .execute(pool)
+            .await
+            .map_err(|e| format!("Failed to store hash for document {}: {}", id, e))?
+            .rows_affected();
+    }
+    Ok(updated)
+}
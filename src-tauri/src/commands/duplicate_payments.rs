@@ -0,0 +1,184 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::SqliteConnection;
+use super::app_settings::get_setting;
+use super::import_parser::ParsedCheque;
+
+pub const DUPLICATE_RULES_KEY: &str = "duplicate_payment_rules";
+
+/// Which cheques count as a possible duplicate payment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateRules {
+    /// Flag a cheque number already used on the same account
+    pub same_cheque_number: bool,
+    /// Flag the same payee and amount issued within this many days; `None` disables the rule
+    pub same_payee_amount_days: Option<u32>,
+}
+
+impl Default for DuplicateRules {
+    fn default() -> Self {
+        DuplicateRules {
+            same_cheque_number: true,
+            same_payee_amount_days: Some(30),
+        }
+    }
+}
+
+/// The fields of a cheque that the duplicate rules look at
+#[derive(Debug, Clone)]
+pub struct PaymentCandidate<'a> {
+    pub account_number: Option<&'a str>,
+    pub cheque_number: &'a str,
+    pub client_name: &'a str,
+    pub amount: Decimal,
+    /// `amount` in the currency's minor units, as it is stored
+    pub amount_minor: i64,
+    pub currency: &'a str,
+    /// Cheque being re-checked, excluded from its own matches
+    pub cheque_id: Option<i64>,
+}
+
+pub async fn load_duplicate_rules(conn: &mut SqliteConnection) -> Result<DuplicateRules, String> {
+    Ok(get_setting(conn, DUPLICATE_RULES_KEY).await?.unwrap_or_default())
+}
+
+/// Describe every existing, non-declined cheque that `candidate` may duplicate.
+/// An empty result means the payment is not suspected to be a duplicate.
+pub async fn find_duplicate_payments(
+    conn: &mut SqliteConnection,
+    rules: &DuplicateRules,
+    candidate: &PaymentCandidate<'_>,
+) -> Result<Vec<String>, String> {
+    let mut matches: Vec<String> = Vec::new();
+    let exclude_id = candidate.cheque_id.unwrap_or(-1);
+
+    if rules.same_cheque_number {
+        let rows: Vec<(i64, i64, String)> = sqlx::query_as(
+            "SELECT c.id, c.document_id, d.file_name
+             FROM cheques c JOIN documents d ON d.id = c.document_id
+             WHERE c.cheque_number = ? AND c.account_number IS ? AND c.id != ?
+               AND COALESCE(c.status, 'Pending') != 'Declined'"
+        )
+        .bind(candidate.cheque_number)
+        .bind(candidate.account_number)
+        .bind(exclude_id)
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| format!("Failed to check for duplicate cheque numbers: {}", e))?;
+
+        matches.extend(rows.into_iter().map(|(id, document_id, file_name)| format!(
+            "Cheque number {} already used by cheque {} in '{}' (document {})",
+            candidate.cheque_number, id, file_name, document_id
+        )));
+    }
+
+    if let Some(days) = rules.same_payee_amount_days {
+        let rows: Vec<(i64, i64, String)> = sqlx::query_as(
+            "SELECT c.id, c.document_id, d.file_name
+             FROM cheques c JOIN documents d ON d.id = c.document_id
+             WHERE c.amount_minor = ? AND c.currency = ?
+               AND LOWER(TRIM(c.client_name)) = LOWER(TRIM(?))
+               AND c.id != ? AND COALESCE(c.status, 'Pending') != 'Declined'
+               AND julianday(c.created_at) >= julianday('now', ?)"
+        )
+        .bind(candidate.amount_minor)
+        .bind(candidate.currency)
+        .bind(candidate.client_name)
+        .bind(exclude_id)
+        .bind(format!("-{} days", days))
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| format!("Failed to check for duplicate payees: {}", e))?;
+
+        matches.extend(rows.into_iter().map(|(id, document_id, file_name)| format!(
+            "Same payee and amount ({} {}) as cheque {} in '{}' (document {}) within {} days",
+            candidate.currency, candidate.amount, id, file_name, document_id, days
+        )));
+    }
+
+    Ok(matches)
+}
+
+/// Describe the earlier rows of the same upload that each cheque may duplicate.
+/// Rows are checked against each other here because none of them are saved
+/// until the whole file has been checked against the database.
+pub fn find_duplicates_within_upload(rules: &DuplicateRules, cheques: &[ParsedCheque]) -> Vec<Vec<String>> {
+    let mut by_number: HashMap<(Option<&str>, &str), &ParsedCheque> = HashMap::new();
+    let mut by_payee: HashMap<(String, &str, i64), &ParsedCheque> = HashMap::new();
+
+    cheques
+        .iter()
+        .map(|cheque| {
+            let mut matches: Vec<String> = Vec::new();
+
+            if rules.same_cheque_number {
+                let key = (cheque.account_number.as_deref(), cheque.cheque_number.as_str());
+                match by_number.entry(key) {
+                    Entry::Occupied(first) => matches.push(format!(
+                        "Cheque number {} repeats {} row {} of this file",
+                        cheque.cheque_number, first.get().sheet_name, first.get().row_number
+                    )),
+                    Entry::Vacant(slot) => {
+                        slot.insert(cheque);
+                    }
+                }
+            }
+
+            if rules.same_payee_amount_days.is_some() {
+                let key = (
+                    cheque.client_name.trim().to_lowercase(),
+                    cheque.currency.as_str(),
+                    cheque.amount_minor,
+                );
+                match by_payee.entry(key) {
+                    Entry::Occupied(first) => matches.push(format!(
+                        "Same payee and amount ({} {}) as {} row {} of this file",
+                        cheque.currency, cheque.amount, first.get().sheet_name, first.get().row_number
+                    )),
+                    Entry::Vacant(slot) => {
+                        slot.insert(cheque);
+                    }
+                }
+            }
+
+            matches
+        })
+        .collect()
+}
+
+/// Record an explicit override of a duplicate flag in the audit trail. Without
+/// `user_id` the entry has no user and is attributed to 'Unknown'.
+pub async fn record_duplicate_override(
+    conn: &mut SqliteConnection,
+    cheque_id: i64,
+    flag: &str,
+    reason: &str,
+    user_id: Option<i64>,
+) -> Result<(), String> {
+    sqlx::query(
+        "UPDATE cheques SET duplicate_override_reason = ? WHERE id = ?"
+    )
+    .bind(reason)
+    .bind(cheque_id)
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| format!("Failed to record duplicate override: {}", e))?;
+
+    sqlx::query(
+        "INSERT INTO audit_trail (cheque_id, action_type, old_value, new_value, user_id, user_name, notes)
+         VALUES (?, 'duplicate_override', ?, ?, ?, COALESCE((SELECT username FROM users WHERE id = ?), 'Unknown'), ?)"
+    )
+    .bind(cheque_id)
+    .bind(flag)
+    .bind(reason)
+    .bind(user_id)
+    .bind(user_id)
+    .bind(format!("Duplicate payment check overridden: {}", reason))
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| format!("Failed to write audit trail: {}", e))?;
+
+    Ok(())
+}
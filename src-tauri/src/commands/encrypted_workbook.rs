@@ -0,0 +1,493 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+use aes::cipher::{block_padding::NoPadding, BlockDecryptMut, KeyIvInit};
+use base64::Engine;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use ring::hmac;
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use super::spreadsheet::OLE_SIGNATURE;
+
+const ENCRYPTION_INFO_STREAM: &str = "/EncryptionInfo";
+const ENCRYPTED_PACKAGE_STREAM: &str = "/EncryptedPackage";
+/// EncryptedPackage data is encrypted in independent segments of this size
+const SEGMENT_LEN: usize = 4096;
+
+// Block keys from MS-OFFCRYPTO 2.3.4.11 and 2.3.4.14
+const VERIFIER_INPUT_BLOCK: [u8; 8] = [0xfe, 0xa7, 0xd2, 0x76, 0x3b, 0x4b, 0x9e, 0x79];
+const VERIFIER_VALUE_BLOCK: [u8; 8] = [0xd7, 0xaa, 0x0f, 0x6d, 0x30, 0x61, 0x34, 0x4e];
+const KEY_VALUE_BLOCK: [u8; 8] = [0x14, 0x6e, 0x0b, 0xe7, 0xab, 0xac, 0xd0, 0xd6];
+const INTEGRITY_KEY_BLOCK: [u8; 8] = [0x5f, 0xb2, 0xad, 0x01, 0x0c, 0xb9, 0xe1, 0xf6];
+const INTEGRITY_VALUE_BLOCK: [u8; 8] = [0xa0, 0x67, 0x7f, 0x02, 0xb2, 0x2c, 0x84, 0x33];
+
+/// Whether `data` is an Office workbook encrypted with a password. Such files
+/// are compound documents holding an `EncryptedPackage` instead of a workbook.
+pub fn is_encrypted_workbook(data: &[u8]) -> bool {
+    data.starts_with(OLE_SIGNATURE)
+        && cfb::CompoundFile::open(Cursor::new(data))
+            .map(|compound| compound.exists(ENCRYPTION_INFO_STREAM) && compound.exists(ENCRYPTED_PACKAGE_STREAM))
+            .unwrap_or(false)
+}
+
+/// The upload as a readable workbook: decrypted in memory with `password` when
+/// it is encrypted, otherwise unchanged. Nothing decrypted is written anywhere.
+pub fn unlock_upload<'a>(data: &'a [u8], file_name: &str, password: Option<&str>) -> Result<Cow<'a, [u8]>, String> {
+    if !is_encrypted_workbook(data) {
+        return Ok(Cow::Borrowed(data));
+    }
+    match password.filter(|password| !password.is_empty()) {
+        Some(password) => decrypt_workbook(data, password)
+            .map(Cow::Owned)
+            .map_err(|e| format!("Failed to open '{}': {}", file_name, e)),
+        None => Err(password_required_message(file_name)),
+    }
+}
+
+pub fn password_required_message(file_name: &str) -> String {
+    format!("'{}' is password protected. Enter its password to import it.", file_name)
+}
+
+/// Decrypt an ECMA-376 agile-encrypted workbook, checking the password and the
+/// package's integrity HMAC before returning the plain XLSX bytes
+pub fn decrypt_workbook(data: &[u8], password: &str) -> Result<Vec<u8>, String> {
+    let mut compound = cfb::CompoundFile::open(Cursor::new(data))
+        .map_err(|e| format!("Encrypted workbook is damaged: {}", e))?;
+    let info = read_stream(&mut compound, ENCRYPTION_INFO_STREAM)?;
+    let package = read_stream(&mut compound, ENCRYPTED_PACKAGE_STREAM)?;
+
+    let info = EncryptionInfo::parse(&info)?;
+    let secret_key = info.password.unlock(password, info.key_data.key_len())?;
+    info.verify_integrity(&secret_key, &package)?;
+    info.key_data.decrypt_package(&secret_key, &package)
+}
+
+#[derive(Debug, Clone, Copy)]
+enum HashAlgorithm {
+    Sha1,
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl HashAlgorithm {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "SHA1" => Ok(HashAlgorithm::Sha1),
+            "SHA256" => Ok(HashAlgorithm::Sha256),
+            "SHA384" => Ok(HashAlgorithm::Sha384),
+            "SHA512" => Ok(HashAlgorithm::Sha512),
+            other => Err(format!("Unsupported encryption hash algorithm '{}'", other)),
+        }
+    }
+
+    fn digest(&self, parts: &[&[u8]]) -> Vec<u8> {
+        fn run<D: Digest>(parts: &[&[u8]]) -> Vec<u8> {
+            let mut hasher = D::new();
+            for part in parts {
+                hasher.update(part);
+            }
+            hasher.finalize().to_vec()
+        }
+        match self {
+            HashAlgorithm::Sha1 => run::<Sha1>(parts),
+            HashAlgorithm::Sha256 => run::<Sha256>(parts),
+            HashAlgorithm::Sha384 => run::<Sha384>(parts),
+            HashAlgorithm::Sha512 => run::<Sha512>(parts),
+        }
+    }
+
+    fn hmac(&self) -> hmac::Algorithm {
+        match self {
+            HashAlgorithm::Sha1 => hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY,
+            HashAlgorithm::Sha256 => hmac::HMAC_SHA256,
+            HashAlgorithm::Sha384 => hmac::HMAC_SHA384,
+            HashAlgorithm::Sha512 => hmac::HMAC_SHA512,
+        }
+    }
+}
+
+/// Cipher settings shared by the `keyData` and password `encryptedKey` elements
+#[derive(Debug)]
+struct CipherParams {
+    salt: Vec<u8>,
+    block_size: usize,
+    key_bits: usize,
+    hash: HashAlgorithm,
+}
+
+impl CipherParams {
+    fn parse(attributes: &HashMap<String, String>, element: &str) -> Result<Self, String> {
+        let cipher = attribute(attributes, element, "cipherAlgorithm")?;
+        let chaining = attribute(attributes, element, "cipherChaining")?;
+        if cipher != "AES" || chaining != "ChainingModeCBC" {
+            return Err(format!("Unsupported workbook cipher {} {}", cipher, chaining));
+        }
+        Ok(CipherParams {
+            salt: decode_base64(attribute(attributes, element, "saltValue")?)?,
+            block_size: parse_number(attributes, element, "blockSize")?,
+            key_bits: parse_number(attributes, element, "keyBits")?,
+            hash: HashAlgorithm::parse(attribute(attributes, element, "hashAlgorithm")?)?,
+        })
+    }
+
+    fn key_len(&self) -> usize {
+        self.key_bits / 8
+    }
+
+    /// Initialisation vector derived from the salt and a block key or segment index
+    fn derived_iv(&self, block: &[u8]) -> Vec<u8> {
+        fit(self.hash.digest(&[&self.salt, block]), self.block_size)
+    }
+
+    fn decrypt_package(&self, secret_key: &[u8], package: &[u8]) -> Result<Vec<u8>, String> {
+        let (size, encrypted) = package
+            .split_first_chunk::<8>()
+            .ok_or_else(|| "Encrypted package is truncated".to_string())?;
+        let size = u64::from_le_bytes(*size) as usize;
+
+        let mut plain = Vec::with_capacity(encrypted.len());
+        for (index, segment) in encrypted.chunks(SEGMENT_LEN).enumerate() {
+            let iv = self.derived_iv(&(index as u32).to_le_bytes());
+            plain.extend(aes_cbc_decrypt(secret_key, &iv, segment)?);
+        }
+        if plain.len() < size {
+            return Err("Encrypted package is truncated".to_string());
+        }
+        plain.truncate(size);
+        Ok(plain)
+    }
+}
+
+/// Password key encryptor: verifies the password and wraps the package key
+#[derive(Debug)]
+struct PasswordKey {
+    params: CipherParams,
+    spin_count: u32,
+    verifier_input: Vec<u8>,
+    verifier_hash: Vec<u8>,
+    key_value: Vec<u8>,
+}
+
+impl PasswordKey {
+    /// Recover the package's secret key, failing when the password is wrong
+    fn unlock(&self, password: &str, secret_len: usize) -> Result<Vec<u8>, String> {
+        let hash = self.params.hash;
+        let password: Vec<u8> = password.encode_utf16().flat_map(|unit| unit.to_le_bytes()).collect();
+        let mut hashed = hash.digest(&[&self.params.salt, &password]);
+        for iteration in 0..self.spin_count {
+            hashed = hash.digest(&[&iteration.to_le_bytes(), &hashed]);
+        }
+
+        let iv = fit(self.params.salt.clone(), self.params.block_size);
+        let decrypt = |block: &[u8], value: &[u8]| {
+            let key = fit(hash.digest(&[&hashed, block]), self.params.key_len());
+            aes_cbc_decrypt(&key, &iv, value)
+        };
+
+        let mut verifier = decrypt(&VERIFIER_INPUT_BLOCK, &self.verifier_input)?;
+        verifier.truncate(self.params.salt.len());
+        let mut expected = decrypt(&VERIFIER_VALUE_BLOCK, &self.verifier_hash)?;
+        let verifier_hash = hash.digest(&[&verifier]);
+        expected.truncate(verifier_hash.len());
+        if verifier_hash != expected {
+            return Err("Incorrect password".to_string());
+        }
+
+        let mut secret_key = decrypt(&KEY_VALUE_BLOCK, &self.key_value)?;
+        if secret_key.len() < secret_len {
+            return Err("Encrypted workbook key is damaged".to_string());
+        }
+        secret_key.truncate(secret_len);
+        Ok(secret_key)
+    }
+}
+
+/// Contents of the `EncryptionInfo` stream of an agile-encrypted package
+#[derive(Debug)]
+struct EncryptionInfo {
+    key_data: CipherParams,
+    /// Encrypted HMAC key and value protecting the whole package
+    integrity: Option<(Vec<u8>, Vec<u8>)>,
+    password: PasswordKey,
+}
+
+impl EncryptionInfo {
+    fn parse(stream: &[u8]) -> Result<Self, String> {
+        let (version, xml) = stream
+            .split_at_checked(8)
+            .ok_or_else(|| "Encryption info is truncated".to_string())?;
+        let (major, minor) = (
+            u16::from_le_bytes([version[0], version[1]]),
+            u16::from_le_bytes([version[2], version[3]]),
+        );
+        if (major, minor) != (4, 4) {
+            return Err(format!(
+                "Only agile encryption is supported, not version {}.{}. Re-save the workbook in a current version of Excel.",
+                major, minor
+            ));
+        }
+        let xml = std::str::from_utf8(xml).map_err(|_| "Encryption info is not valid XML".to_string())?;
+
+        let mut key_data = None;
+        let mut integrity = None;
+        let mut password = None;
+        let mut reader = Reader::from_str(xml);
+        loop {
+            match reader.read_event() {
+                Ok(Event::Start(element)) | Ok(Event::Empty(element)) => {
+                    let name = String::from_utf8_lossy(element.local_name().as_ref()).into_owned();
+                    let mut attributes: HashMap<String, String> = HashMap::new();
+                    for attr in element.attributes().flatten() {
+                        let key = String::from_utf8_lossy(attr.key.local_name().as_ref()).into_owned();
+                        let value = attr.decode_and_unescape_value(&reader).map_err(|e| format!("Encryption info is invalid: {}", e))?;
+                        attributes.insert(key, value.into_owned());
+                    }
+                    match name.as_str() {
+                        "keyData" => key_data = Some(CipherParams::parse(&attributes, &name)?),
+                        "dataIntegrity" => {
+                            integrity = Some((
+                                decode_base64(attribute(&attributes, &name, "encryptedHmacKey")?)?,
+                                decode_base64(attribute(&attributes, &name, "encryptedHmacValue")?)?,
+                            ));
+                        }
+                        // Certificate key encryptors share the element name but have no spin count
+                        "encryptedKey" if attributes.contains_key("spinCount") => {
+                            password = Some(PasswordKey {
+                                params: CipherParams::parse(&attributes, &name)?,
+                                spin_count: parse_number(&attributes, &name, "spinCount")?,
+                                verifier_input: decode_base64(attribute(&attributes, &name, "encryptedVerifierHashInput")?)?,
+                                verifier_hash: decode_base64(attribute(&attributes, &name, "encryptedVerifierHashValue")?)?,
+                                key_value: decode_base64(attribute(&attributes, &name, "encryptedKeyValue")?)?,
+                            });
+                        }
+                        _ => {}
+                    }
+                }
+                Ok(Event::Eof) => break,
+                Ok(_) => {}
+                Err(e) => return Err(format!("Encryption info is invalid: {}", e)),
+            }
+        }
+
+        Ok(EncryptionInfo {
+            key_data: key_data.ok_or_else(|| "Encryption info has no key data".to_string())?,
+            integrity,
+            password: password.ok_or_else(|| "Workbook is not protected with a password".to_string())?,
+        })
+    }
+
+    /// Check the package HMAC so a damaged or altered file is never imported
+    fn verify_integrity(&self, secret_key: &[u8], package: &[u8]) -> Result<(), String> {
+        let Some((encrypted_key, encrypted_value)) = &self.integrity else {
+            return Ok(());
+        };
+        let hash = self.key_data.hash;
+        let hash_len = hash.digest(&[]).len();
+
+        let mut hmac_key = aes_cbc_decrypt(secret_key, &self.key_data.derived_iv(&INTEGRITY_KEY_BLOCK), encrypted_key)?;
+        let mut hmac_value =
+            aes_cbc_decrypt(secret_key, &self.key_data.derived_iv(&INTEGRITY_VALUE_BLOCK), encrypted_value)?;
+        hmac_key.truncate(hash_len);
+        hmac_value.truncate(hash_len);
+
+        hmac::verify(&hmac::Key::new(hash.hmac(), &hmac_key), package, &hmac_value)
+            .map_err(|_| "Encrypted workbook failed its integrity check; it may be damaged or altered".to_string())
+    }
+}
+
+fn read_stream(compound: &mut cfb::CompoundFile<Cursor<&[u8]>>, path: &str) -> Result<Vec<u8>, String> {
+    let mut stream = compound
+        .open_stream(path)
+        .map_err(|e| format!("Encrypted workbook has no {} stream: {}", path.trim_start_matches('/'), e))?;
+    let mut data = Vec::new();
+    stream
+        .read_to_end(&mut data)
+        .map_err(|e| format!("Failed to read {}: {}", path.trim_start_matches('/'), e))?;
+    Ok(data)
+}
+
+/// AES-CBC without padding, choosing AES-128, -192 or -256 from the key length
+fn aes_cbc_decrypt(key: &[u8], iv: &[u8], data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut buffer = data.to_vec();
+    let result = match key.len() {
+        16 => cbc::Decryptor::<aes::Aes128>::new_from_slices(key, iv)
+            .map_err(|e| e.to_string())?
+            .decrypt_padded_mut::<NoPadding>(&mut buffer)
+            .map(|plain| plain.len()),
+        24 => cbc::Decryptor::<aes::Aes192>::new_from_slices(key, iv)
+            .map_err(|e| e.to_string())?
+            .decrypt_padded_mut::<NoPadding>(&mut buffer)
+            .map(|plain| plain.len()),
+        32 => cbc::Decryptor::<aes::Aes256>::new_from_slices(key, iv)
+            .map_err(|e| e.to_string())?
+            .decrypt_padded_mut::<NoPadding>(&mut buffer)
+            .map(|plain| plain.len()),
+        other => return Err(format!("Unsupported AES key length of {} bytes", other)),
+    };
+    let len = result.map_err(|_| "Encrypted data is not a whole number of AES blocks".to_string())?;
+    buffer.truncate(len);
+    Ok(buffer)
+}
+
+/// Truncate `value` to `len` bytes, or pad it with 0x36 as the specification requires
+fn fit(mut value: Vec<u8>, len: usize) -> Vec<u8> {
+    value.resize(len, 0x36);
+    value
+}
+
+fn attribute<'a>(attributes: &'a HashMap<String, String>, element: &str, name: &str) -> Result<&'a str, String> {
+    attributes
+        .get(name)
+        .map(String::as_str)
+        .ok_or_else(|| format!("Encryption info {} has no {}", element, name))
+}
+
+fn parse_number<T: std::str::FromStr>(attributes: &HashMap<String, String>, element: &str, name: &str) -> Result<T, String> {
+    attribute(attributes, element, name)?
+        .parse()
+        .map_err(|_| format!("Encryption info {} has an invalid {}", element, name))
+}
+
+fn decode_base64(value: &str) -> Result<Vec<u8>, String> {
+    base64::engine::general_purpose::STANDARD
+        .decode(value)
+        .map_err(|_| "Encryption info holds invalid base64".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aes::cipher::BlockEncryptMut;
+    use std::io::Write;
+
+    const PASSWORD: &str = "s3cret€";
+    const SPIN_COUNT: u32 = 1000;
+
+    fn sha512(parts: &[&[u8]]) -> Vec<u8> {
+        let mut digest = Sha512::new();
+        for part in parts {
+            digest.update(part);
+        }
+        digest.finalize().to_vec()
+    }
+
+    fn aes256_cbc_encrypt(key: &[u8], iv: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut buffer = data.to_vec();
+        buffer.resize(data.len().div_ceil(16) * 16, 0);
+        let len = buffer.len();
+        cbc::Encryptor::<aes::Aes256>::new_from_slices(key, &iv[..16])
+            .unwrap()
+            .encrypt_padded_mut::<NoPadding>(&mut buffer, len)
+            .unwrap()
+            .to_vec()
+    }
+
+    fn base64(data: &[u8]) -> String {
+        base64::engine::general_purpose::STANDARD.encode(data)
+    }
+
+    fn compound_file(info: &[u8], package: &[u8]) -> Vec<u8> {
+        let mut compound = cfb::CompoundFile::create(Cursor::new(Vec::new())).unwrap();
+        compound.create_stream(ENCRYPTION_INFO_STREAM).unwrap().write_all(info).unwrap();
+        compound.create_stream(ENCRYPTED_PACKAGE_STREAM).unwrap().write_all(package).unwrap();
+        compound.flush().unwrap();
+        compound.into_inner().into_inner()
+    }
+
+    /// Agile-encrypt `plain` with SHA-512 and AES-256, as Excel does, handing
+    /// the finished package to `alter` before it is stored
+    fn encrypt(plain: &[u8], password: &str, alter: impl FnOnce(&mut Vec<u8>)) -> Vec<u8> {
+        let key_salt = [7u8; 16];
+        let password_salt = [9u8; 16];
+        let secret_key = [0x42u8; 32];
+        let verifier = [3u8; 16];
+        let hmac_key = [0x11u8; 64];
+
+        let password: Vec<u8> = password.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        let mut hash = sha512(&[&password_salt, &password]);
+        for iteration in 0..SPIN_COUNT {
+            hash = sha512(&[&iteration.to_le_bytes(), &hash]);
+        }
+        let block_key = |block: &[u8]| fit(sha512(&[&hash, block]), 32);
+        let data_iv = |block: &[u8]| fit(sha512(&[&key_salt, block]), 16);
+
+        let verifier_input = aes256_cbc_encrypt(&block_key(&VERIFIER_INPUT_BLOCK), &password_salt, &verifier);
+        let verifier_value =
+            aes256_cbc_encrypt(&block_key(&VERIFIER_VALUE_BLOCK), &password_salt, &sha512(&[&verifier]));
+        let key_value = aes256_cbc_encrypt(&block_key(&KEY_VALUE_BLOCK), &password_salt, &secret_key);
+
+        let mut package = (plain.len() as u64).to_le_bytes().to_vec();
+        for (index, segment) in plain.chunks(SEGMENT_LEN).enumerate() {
+            package.extend(aes256_cbc_encrypt(&secret_key, &data_iv(&(index as u32).to_le_bytes()), segment));
+        }
+        let tag = hmac::sign(&hmac::Key::new(hmac::HMAC_SHA512, &hmac_key), &package);
+        let integrity_key = aes256_cbc_encrypt(&secret_key, &data_iv(&INTEGRITY_KEY_BLOCK), &hmac_key);
+        let integrity_value = aes256_cbc_encrypt(&secret_key, &data_iv(&INTEGRITY_VALUE_BLOCK), tag.as_ref());
+        alter(&mut package);
+
+        let cipher = r#"saltSize="16" blockSize="16" keyBits="256" hashSize="64" cipherAlgorithm="AES" cipherChaining="ChainingModeCBC" hashAlgorithm="SHA512""#;
+        let xml = format!(
+            concat!(
+                r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+                r#"<encryption xmlns="http://schemas.microsoft.com/office/2006/encryption" xmlns:p="http://schemas.microsoft.com/office/2006/keyEncryptor/password">"#,
+                r#"<keyData {cipher} saltValue="{}"/>"#,
+                r#"<dataIntegrity encryptedHmacKey="{}" encryptedHmacValue="{}"/>"#,
+                r#"<keyEncryptors><keyEncryptor uri="http://schemas.microsoft.com/office/2006/keyEncryptor/password">"#,
+                r#"<p:encryptedKey spinCount="{}" {cipher} saltValue="{}" encryptedVerifierHashInput="{}" encryptedVerifierHashValue="{}" encryptedKeyValue="{}"/>"#,
+                r#"</keyEncryptor></keyEncryptors></encryption>"#,
+            ),
+            base64(&key_salt),
+            base64(&integrity_key),
+            base64(&integrity_value),
+            SPIN_COUNT,
+            base64(&password_salt),
+            base64(&verifier_input),
+            base64(&verifier_value),
+            base64(&key_value),
+            cipher = cipher,
+        );
+        let mut info = vec![4, 0, 4, 0, 0x40, 0, 0, 0];
+        info.extend(xml.as_bytes());
+        compound_file(&info, &package)
+    }
+
+    fn plain_workbook() -> Vec<u8> {
+        // Long enough to span several segments, with a partial last one
+        (0..10_000u32).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn decrypts_with_the_right_password() {
+        let plain = plain_workbook();
+        let encrypted = encrypt(&plain, PASSWORD, |_| {});
+        assert!(is_encrypted_workbook(&encrypted));
+        assert!(!is_encrypted_workbook(&plain));
+        assert_eq!(decrypt_workbook(&encrypted, PASSWORD).unwrap(), plain);
+        assert_eq!(unlock_upload(&encrypted, "p.xlsx", Some(PASSWORD)).unwrap().as_ref(), plain.as_slice());
+    }
+
+    #[test]
+    fn rejects_a_wrong_password() {
+        let encrypted = encrypt(&plain_workbook(), PASSWORD, |_| {});
+        assert_eq!(decrypt_workbook(&encrypted, "s3cret").unwrap_err(), "Incorrect password");
+        assert_eq!(
+            unlock_upload(&encrypted, "p.xlsx", Some("wrong")).unwrap_err(),
+            "Failed to open 'p.xlsx': Incorrect password"
+        );
+        assert_eq!(unlock_upload(&encrypted, "p.xlsx", None).unwrap_err(), password_required_message("p.xlsx"));
+    }
+
+    #[test]
+    fn rejects_an_altered_package() {
+        let encrypted = encrypt(&plain_workbook(), PASSWORD, |package| package[100] ^= 1);
+        assert_eq!(
+            decrypt_workbook(&encrypted, PASSWORD).unwrap_err(),
+            "Encrypted workbook failed its integrity check; it may be damaged or altered"
+        );
+
+        let truncated = encrypt(&plain_workbook(), PASSWORD, |package| package.truncate(package.len() - 16));
+        assert!(decrypt_workbook(&truncated, PASSWORD).unwrap_err().contains("integrity check"));
+    }
+}
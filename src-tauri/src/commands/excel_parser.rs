@@ -1,48 +1,106 @@
-use calamine::{Data, Reader, Xlsx};
 use serde_json::json;
-use std::io::Cursor;
-use rand::Rng; // For random ID generation
-use chrono::Utc;
+use sqlx::SqlitePool;
+use tauri::State;
+use super::column_mapping::load_mapping;
+use super::control_totals::check_control_totals;
+use super::duplicate_payments::{
+    find_duplicate_payments, find_duplicates_within_upload, load_duplicate_rules, PaymentCandidate,
+};
+use super::encrypted_workbook::unlock_upload;
+use super::import_parser::{parse_upload, ImportOptions};
+use super::payment_limits::with_payment_limits;
+use super::spreadsheet::sheet_names;
 
+/// List the sheets of an uploaded file so the user can choose which to import.
+/// Encrypted workbooks need their password.
 #[tauri::command]
-pub fn parse_excel_to_cheques(file_data: Vec<u8>, file_name: String) -> Result<String, String> {
-    println!("Processing file: {}", file_name); // Print the file name
-    let cursor: Cursor<Vec<u8>> = Cursor::new(file_data);
+pub async fn list_spreadsheet_sheets(
+    file_data: Vec<u8>,
+    file_name: String,
+    password: Option<String>,
+) -> Result<Vec<String>, String> {
+    let data = unlock_upload(&file_data, &file_name, password.as_deref())?;
+    sheet_names(&data, &file_name)
+}
 
-    let mut workbook: Xlsx<Cursor<Vec<u8>>> = Xlsx::new(cursor).map_err(|e| e.to_string())?;
+#[tauri::command]
+pub async fn parse_excel_to_cheques(
+    file_data: Vec<u8>,
+    file_name: String,
+    options: Option<ImportOptions>,
+    pool: State<'_, SqlitePool>,
+) -> Result<String, String> {
+    println!("Processing file: {}", file_name); // Print the file name
+    let options = options.unwrap_or_default();
+    let mapping = load_mapping(pool.inner(), options.profile_id).await?;
+    let mut conn = pool
+        .acquire()
+        .await
+        .map_err(|e| format!("Failed to connect to database: {}", e))?;
+    let options = with_payment_limits(&mut conn, &options).await?;
 
-    // Access the worksheet
-    let mut records: Vec<serde_json::Map<String, serde_json::Value>> = Vec::new();
-    let today: String = Utc::now().format("%Y-%m-%d").to_string();
-    match workbook.worksheet_range("Sheet1") {
-        Ok(range) => {
-            for row in range.rows().skip(1) {
-                let mut record: serde_json::Map<String, serde_json::Value> = serde_json::Map::new();
-                let random_id: i32 = rand::thread_rng().gen_range(1000..999999);
-                record.insert("cheque_id".to_string(), json!(random_id));
-                record.insert("file_name".to_string(), json!(file_name.clone())); // Add file_name to the record
-                record.insert("issue_date".to_string(), json!(today));
-                record.insert("date".to_string(), json!(today));
+    // Same parser as upload_excel_as_blob, so the preview shows exactly what will be saved
+    let parsed = parse_upload(&file_data, &file_name, &mapping, &options)?;
 
-                for (i, cell) in row.iter().enumerate() {
-                    let key: &str = match i {
-                        0 => "cheque_number",
-                        1 => "amount",
-                        2 => "client_name",
-                        _ => "unknown",
-                    };
-                    let value: serde_json::Value = match cell {
-                        Data::Float(f) => json!(f),
-                        Data::Int(i) => json!(i),
-                        Data::String(s) => json!(s),
-                        _ => json!(null),
-                    };
-                    record.insert(key.to_string(), value);
-                }
-                records.push(record);
-            }
-            Ok(serde_json::to_string(&records).map_err(|e| e.to_string())?)
-        }
-        Err(e) => Err(format!("Failed to read the worksheet or range: {:?}", e)),
+    // Show which rows the import will flag as possible duplicate payments
+    let rules = load_duplicate_rules(&mut conn).await?;
+    let within_upload = find_duplicates_within_upload(&rules, &parsed.cheques);
+    let mut duplicate_flags: Vec<Option<String>> = Vec::with_capacity(parsed.cheques.len());
+    for (cheque, mut matches) in parsed.cheques.iter().zip(within_upload) {
+        matches.extend(find_duplicate_payments(&mut conn, &rules, &PaymentCandidate {
+            account_number: cheque.account_number.as_deref(),
+            cheque_number: &cheque.cheque_number,
+            client_name: &cheque.client_name,
+            amount: cheque.amount,
+            amount_minor: cheque.amount_minor,
+            currency: &cheque.currency,
+            cheque_id: None,
+        })
+        .await?);
+        duplicate_flags.push((!matches.is_empty()).then(|| matches.join("; ")));
     }
+
+    let records: Vec<serde_json::Value> = parsed
+        .cheques
+        .iter()
+        .zip(duplicate_flags)
+        .enumerate()
+        .map(|(index, (cheque, duplicate_flag))| {
+            json!({
+                // Preview-only identifier; stable for a given file and unique within it
+                "cheque_id": index + 1,
+                "file_name": file_name,
+                "issue_date": cheque.issue_date,
+                "date": cheque.issue_date,
+                "memo": cheque.memo,
+                "remarks": cheque.remarks,
+                "address_line1": cheque.address.address_line1,
+                "address_line2": cheque.address.address_line2,
+                "city": cheque.address.city,
+                "region": cheque.address.region,
+                "postal_code": cheque.address.postal_code,
+                "country": cheque.address.country,
+                "delivery_method": cheque.delivery_method,
+                "sheet_name": cheque.sheet_name,
+                "row_number": cheque.row_number,
+                "cheque_number": cheque.cheque_number,
+                "amount": cheque.amount,
+                "amount_minor": cheque.amount_minor,
+                "currency": cheque.currency,
+                "client_name": cheque.client_name,
+                "account_number": cheque.account_number,
+                "duplicate_flag": duplicate_flag
+            })
+        })
+        .collect();
+
+    let response: serde_json::Value = json!({
+        "cheques": records,
+        "rejected_count": parsed.rejected_rows,
+        "issues": parsed.issues,
+        "skipped_sheets": parsed.skipped_sheets,
+        "control_totals": check_control_totals(&parsed.declared_totals, &parsed.cheques)
+    });
+
+    serde_json::to_string(&response).map_err(|e| e.to_string())
 }
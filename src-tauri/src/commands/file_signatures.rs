@@ -0,0 +1,389 @@
+use std::collections::HashMap;
+use base64::Engine;
+use ring::{hmac, signature};
+use serde::{Deserialize, Serialize};
+use sqlx::SqliteConnection;
+use super::app_settings::{get_setting, set_setting};
+
+pub const SIGNATURE_POLICY_KEY: &str = "file_signatures";
+
+const ED25519_PUBLIC_KEY_LEN: usize = 32;
+const MIN_HMAC_KEY_LEN: usize = 16;
+
+/// How a detached file signature is computed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureAlgorithm {
+    /// Ed25519 signature checked with the sender's public key
+    Ed25519,
+    /// HMAC-SHA256 tag keyed with a secret shared with the sender
+    HmacSha256,
+}
+
+impl SignatureAlgorithm {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SignatureAlgorithm::Ed25519 => "ed25519",
+            SignatureAlgorithm::HmacSha256 => "hmac_sha256",
+        }
+    }
+}
+
+/// How a key or signature is written as text. Nothing is guessed from the
+/// text itself, since a base64 value may consist of hex digits only.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ByteEncoding {
+    /// Standard or URL-safe base64, with or without padding
+    #[default]
+    Base64,
+    Hex,
+    /// The UTF-8 bytes of the text as written, for passphrase-style HMAC secrets
+    Text,
+}
+
+impl ByteEncoding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ByteEncoding::Base64 => "base64",
+            ByteEncoding::Hex => "hex",
+            ByteEncoding::Text => "text",
+        }
+    }
+
+    pub fn decode(&self, text: &str) -> Option<Vec<u8>> {
+        if let ByteEncoding::Text = self {
+            return (!text.is_empty()).then(|| text.as_bytes().to_vec());
+        }
+        let text: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+        if text.is_empty() {
+            return None;
+        }
+        match self {
+            ByteEncoding::Hex => {
+                if !text.len().is_multiple_of(2) || !text.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return None;
+                }
+                (0..text.len())
+                    .step_by(2)
+                    .map(|i| u8::from_str_radix(&text[i..i + 2], 16).ok())
+                    .collect()
+            }
+            _ => {
+                let unpadded = text.trim_end_matches('=');
+                base64::engine::general_purpose::STANDARD_NO_PAD
+                    .decode(unpadded)
+                    .or_else(|_| base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(unpadded))
+                    .ok()
+            }
+        }
+    }
+}
+
+/// Key that payment files may be signed with
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrustedKey {
+    pub name: String,
+    pub algorithm: SignatureAlgorithm,
+    /// Public key for Ed25519, or shared secret for HMAC, written in `encoding`.
+    /// Shared secrets are stored apart from the policy and never read back, so
+    /// they are blank when the policy is shown; saving a blank secret keeps the
+    /// stored one.
+    #[serde(default)]
+    pub key: String,
+    #[serde(default)]
+    pub encoding: ByteEncoding,
+    /// Whether a shared secret is stored for this HMAC key
+    #[serde(default, skip_deserializing)]
+    pub has_secret: bool,
+}
+
+impl TrustedKey {
+    /// Check that the key decodes and has a usable length
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Trusted key name cannot be empty".to_string());
+        }
+        if self.algorithm == SignatureAlgorithm::Ed25519 && self.encoding == ByteEncoding::Text {
+            return Err(format!("Ed25519 key '{}' must be written in base64 or hex", self.name));
+        }
+        let key = self
+            .encoding
+            .decode(&self.key)
+            .ok_or_else(|| format!("Key '{}' is not valid {}", self.name, self.encoding.as_str()))?;
+        match self.algorithm {
+            SignatureAlgorithm::Ed25519 if key.len() != ED25519_PUBLIC_KEY_LEN => Err(format!(
+                "Ed25519 key '{}' must be {} bytes, not {}",
+                self.name,
+                ED25519_PUBLIC_KEY_LEN,
+                key.len()
+            )),
+            SignatureAlgorithm::HmacSha256 if key.len() < MIN_HMAC_KEY_LEN => Err(format!(
+                "HMAC key '{}' must be at least {} bytes",
+                self.name, MIN_HMAC_KEY_LEN
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn verifies(&self, data: &[u8], signature_bytes: &[u8]) -> bool {
+        let Some(key) = self.encoding.decode(&self.key) else {
+            return false;
+        };
+        match self.algorithm {
+            SignatureAlgorithm::Ed25519 => signature::UnparsedPublicKey::new(&signature::ED25519, &key)
+                .verify(data, signature_bytes)
+                .is_ok(),
+            SignatureAlgorithm::HmacSha256 => {
+                hmac::verify(&hmac::Key::new(hmac::HMAC_SHA256, &key), data, signature_bytes).is_ok()
+            }
+        }
+    }
+}
+
+/// Whether uploads must be signed, and by whom
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SignaturePolicy {
+    /// Reject files that are unsigned or whose signature does not verify
+    pub require_signature: bool,
+    pub trusted_keys: Vec<TrustedKey>,
+    /// How signatures are written unless they start with "base64:" or "hex:"
+    pub signature_encoding: ByteEncoding,
+}
+
+impl SignaturePolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.signature_encoding == ByteEncoding::Text {
+            return Err("Signatures must be written in base64 or hex".to_string());
+        }
+        for (index, key) in self.trusted_keys.iter().enumerate() {
+            key.validate()?;
+            if self.trusted_keys[..index].iter().any(|other| other.name == key.name) {
+                return Err(format!("Trusted key name '{}' is used more than once", key.name));
+            }
+        }
+        if self.require_signature && self.trusted_keys.is_empty() {
+            return Err("Add a trusted key before requiring signed files".to_string());
+        }
+        Ok(())
+    }
+
+    /// The policy without its HMAC secrets, as it may be shown or stored in the settings
+    pub fn redacted(&self) -> SignaturePolicy {
+        let mut policy = self.clone();
+        for key in policy.trusted_keys.iter_mut().filter(|key| key.algorithm == SignatureAlgorithm::HmacSha256) {
+            key.has_secret = !key.key.is_empty();
+            key.key.clear();
+        }
+        policy
+    }
+}
+
+/// Outcome of checking an upload's signature
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureStatus {
+    Verified,
+    Unsigned,
+    Invalid,
+}
+
+impl SignatureStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SignatureStatus::Verified => "verified",
+            SignatureStatus::Unsigned => "unsigned",
+            SignatureStatus::Invalid => "invalid",
+        }
+    }
+}
+
+/// Signature check result stored with the document
+#[derive(Debug, Clone, Serialize)]
+pub struct SignatureVerification {
+    pub status: SignatureStatus,
+    /// Trusted key the signature verified against
+    pub key_name: Option<String>,
+    pub algorithm: Option<SignatureAlgorithm>,
+    /// Detached signature as supplied
+    pub signature: Option<String>,
+    pub detail: String,
+}
+
+/// Load the policy together with the HMAC secrets, which are kept in
+/// `signing_secrets` rather than with the other settings
+pub async fn load_signature_policy(conn: &mut SqliteConnection) -> Result<SignaturePolicy, String> {
+    let mut policy: SignaturePolicy = get_setting(&mut *conn, SIGNATURE_POLICY_KEY).await?.unwrap_or_default();
+    let secrets: HashMap<String, String> = sqlx::query_as::<_, (String, String)>(
+        "SELECT key_name, secret FROM signing_secrets"
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| format!("Failed to read signing secrets: {}", e))?
+    .into_iter()
+    .collect();
+
+    for key in policy.trusted_keys.iter_mut().filter(|key| key.algorithm == SignatureAlgorithm::HmacSha256) {
+        key.key = secrets.get(&key.name).cloned().unwrap_or_default();
+    }
+    Ok(policy)
+}
+
+/// Validate and store the policy, moving HMAC secrets into `signing_secrets`.
+/// An HMAC key saved with a blank secret keeps the secret stored under its name.
+pub async fn save_signature_policy(conn: &mut SqliteConnection, mut policy: SignaturePolicy) -> Result<(), String> {
+    let stored = load_signature_policy(&mut *conn).await?;
+    for key in policy.trusted_keys.iter_mut() {
+        if key.algorithm != SignatureAlgorithm::HmacSha256 || !key.key.trim().is_empty() {
+            continue;
+        }
+        let existing = stored
+            .trusted_keys
+            .iter()
+            .find(|other| other.name == key.name && other.algorithm == key.algorithm && !other.key.is_empty())
+            .ok_or_else(|| format!("Enter the shared secret for HMAC key '{}'", key.name))?;
+        key.key = existing.key.clone();
+        key.encoding = existing.encoding;
+    }
+    policy.validate()?;
+
+    sqlx::query("DELETE FROM signing_secrets")
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| format!("Failed to replace signing secrets: {}", e))?;
+    for key in policy.trusted_keys.iter().filter(|key| key.algorithm == SignatureAlgorithm::HmacSha256) {
+        sqlx::query("INSERT INTO signing_secrets (key_name, secret) VALUES (?, ?)")
+            .bind(&key.name)
+            .bind(&key.key)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| format!("Failed to store the secret for key '{}': {}", key.name, e))?;
+    }
+
+    set_setting(conn, SIGNATURE_POLICY_KEY, &policy.redacted()).await
+}
+
+/// Check a detached signature over `data`. With `key_name` only that trusted
+/// key is tried; otherwise any trusted key may have signed the file.
+pub fn verify_file_signature(
+    policy: &SignaturePolicy,
+    data: &[u8],
+    signature_text: Option<&str>,
+    key_name: Option<&str>,
+) -> SignatureVerification {
+    let signature_text = signature_text.map(str::trim).filter(|text| !text.is_empty());
+    let outcome = |status, key: Option<&TrustedKey>, detail: String| SignatureVerification {
+        status,
+        key_name: key.map(|key| key.name.clone()),
+        algorithm: key.map(|key| key.algorithm),
+        signature: signature_text.map(str::to_string),
+        detail,
+    };
+
+    let Some(text) = signature_text else {
+        return outcome(SignatureStatus::Unsigned, None, "File has no signature".to_string());
+    };
+    let (encoding, encoded) = match (text.strip_prefix("base64:"), text.strip_prefix("hex:")) {
+        (Some(encoded), _) => (ByteEncoding::Base64, encoded),
+        (_, Some(encoded)) => (ByteEncoding::Hex, encoded),
+        _ => (policy.signature_encoding, text),
+    };
+    let Some(signature_bytes) = encoding.decode(encoded) else {
+        return outcome(
+            SignatureStatus::Invalid,
+            None,
+            format!("Signature is not valid {}", encoding.as_str()),
+        );
+    };
+
+    let candidates: Vec<&TrustedKey> = match key_name {
+        Some(name) => match policy.trusted_keys.iter().find(|key| key.name == name) {
+            Some(key) => vec![key],
+            None => {
+                return outcome(SignatureStatus::Invalid, None, format!("'{}' is not a trusted key", name));
+            }
+        },
+        None => policy.trusted_keys.iter().collect(),
+    };
+    if candidates.is_empty() {
+        return outcome(SignatureStatus::Invalid, None, "No trusted keys are configured".to_string());
+    }
+
+    match candidates.into_iter().find(|key| key.verifies(data, &signature_bytes)) {
+        Some(key) => outcome(
+            SignatureStatus::Verified,
+            Some(key),
+            format!("Signed with trusted {} key '{}'", key.algorithm.as_str(), key.name),
+        ),
+        None => outcome(
+            SignatureStatus::Invalid,
+            None,
+            "Signature does not match the file for any trusted key; it may have been altered".to_string(),
+        ),
+    }
+}
+
+/// Rejection message when the policy does not accept a verification result
+pub fn signature_policy_error(
+    file_name: &str,
+    policy: &SignaturePolicy,
+    verification: &SignatureVerification,
+) -> Option<String> {
+    (policy.require_signature && verification.status != SignatureStatus::Verified).then(|| {
+        format!(
+            "File '{}' was not imported: a valid signature from a trusted key is required. {}",
+            file_name, verification.detail
+        )
+    })
+}
+
+/// Text of a detached signature file. Binary signatures are converted to
+/// base64 and marked as such.
+pub fn signature_file_text(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.trim().to_string(),
+        Err(_) => format!("base64:{}", base64::engine::general_purpose::STANDARD.encode(bytes)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_only_the_named_encoding() {
+        // "deadbeef" is valid base64 as well as hex
+        assert_eq!(ByteEncoding::Base64.decode("deadbeef").unwrap(), vec![0x75, 0xe6, 0x9d, 0x6d, 0xe7, 0x9f]);
+        assert_eq!(ByteEncoding::Hex.decode("deadbeef").unwrap(), vec![0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(ByteEncoding::Text.decode("a shared passphrase").unwrap(), b"a shared passphrase");
+        assert!(ByteEncoding::Hex.decode("+f").is_none());
+        assert!(ByteEncoding::Hex.decode("abc").is_none());
+    }
+
+    #[test]
+    fn signature_prefix_overrides_the_policy() {
+        let secret = "a shared passphrase";
+        let data = b"Cheque,Amount\n1,10\n";
+        let tag = hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes()), data);
+        let hex: String = tag.as_ref().iter().map(|b| format!("{:02x}", b)).collect();
+        let policy = SignaturePolicy {
+            trusted_keys: vec![TrustedKey {
+                name: "mac".to_string(),
+                algorithm: SignatureAlgorithm::HmacSha256,
+                key: secret.to_string(),
+                encoding: ByteEncoding::Text,
+                has_secret: false,
+            }],
+            ..Default::default()
+        };
+        policy.validate().unwrap();
+        let check = |signature: &str| verify_file_signature(&policy, data, Some(signature), None).status;
+        assert_eq!(check(&format!("hex:{}", hex)), SignatureStatus::Verified);
+        assert_eq!(check(&hex), SignatureStatus::Invalid);
+        assert_eq!(check(&signature_file_text(tag.as_ref())), SignatureStatus::Verified);
+
+        let redacted = policy.redacted();
+        assert!(redacted.trusted_keys[0].key.is_empty() && redacted.trusted_keys[0].has_secret);
+    }
+}
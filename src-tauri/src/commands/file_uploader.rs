@@ -1,78 +1,397 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
 use serde_json::json;
 use chrono::Utc;
-use sqlx::SqlitePool;
+use sqlx::{SqliteConnection, SqlitePool};
+use tauri::ipc::{InvokeBody, Request};
 use tauri::State;
-use calamine::{Data, Reader, Xlsx};
-use std::io::Cursor;
+use super::column_mapping::load_mapping;
+use super::control_totals::{check_control_totals, control_totals_error};
+use super::document_hash::{content_hash, duplicate_file_error, find_duplicate_document};
+use super::duplicate_payments::{
+    find_duplicate_payments, find_duplicates_within_upload, load_duplicate_rules, PaymentCandidate,
+};
+use super::file_signatures::{load_signature_policy, signature_policy_error, verify_file_signature};
+use super::import_issues::save_import_issues;
+use super::import_parser::{parse_upload_with_progress, ImportOptions, ParseResult, ParsedCheque, RowIssue};
+use super::payment_limits::with_payment_limits;
+use super::permissions::require_admin;
+
+/// Cheques written per INSERT statement, keeping the bound values well under SQLite's limit
+const INSERT_BATCH_SIZE: usize = 250;
+
+pub const IMPORT_CANCELLED: &str = "Import cancelled";
+
+/// Step an import is currently working on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportStage {
+    #[default]
+    Parsing,
+    CheckingDuplicates,
+    Inserting,
+}
+
+/// Running counts reported while an import is in progress
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportProgress {
+    pub stage: ImportStage,
+    /// Valid cheques found so far; the number that will be inserted once parsing ends
+    pub rows_total: usize,
+    pub rows_parsed: usize,
+    pub rows_inserted: usize,
+    pub errors: usize,
+}
+
+/// Document and cheques written by `save_import`
+#[derive(Debug, Clone)]
+pub struct SavedImport {
+    pub document_id: i64,
+    /// Id of each saved cheque, in the order of `ParseResult::cheques`
+    pub cheque_ids: Vec<i64>,
+    /// Summary returned to the window
+    pub response: serde_json::Value,
+}
+
+/// Header naming an upload whose contents are the raw IPC body: base64 of the JSON `UploadHeader`
+pub const UPLOAD_HEADER: &str = "import-upload";
+
+/// File name and options sent alongside a raw upload
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UploadHeader {
+    file_name: String,
+    #[serde(default)]
+    options: Option<ImportOptions>,
+}
+
+/// File sent by the window as the raw body of an import command, so a large
+/// workbook is not copied into a JSON array of numbers on its way over IPC
+pub struct Upload<'a> {
+    pub file_name: String,
+    pub options: ImportOptions,
+    pub data: &'a [u8],
+}
+
+/// Read the file from the body of `request` and its name and options from the `UPLOAD_HEADER`
+pub fn read_upload<'a>(request: &'a Request<'_>) -> Result<Upload<'a>, String> {
+    let InvokeBody::Raw(data) = request.body() else {
+        return Err("Upload must be sent as raw bytes".to_string());
+    };
+    let header = request
+        .headers()
+        .get(UPLOAD_HEADER)
+        .ok_or_else(|| format!("Upload is missing the {} header", UPLOAD_HEADER))?;
+    let header = base64::engine::general_purpose::STANDARD
+        .decode(header.as_bytes())
+        .map_err(|e| format!("Invalid {} header: {}", UPLOAD_HEADER, e))?;
+    let header: UploadHeader = serde_json::from_slice(&header)
+        .map_err(|e| format!("Invalid {} header: {}", UPLOAD_HEADER, e))?;
+
+    Ok(Upload {
+        file_name: header.file_name,
+        options: header.options.unwrap_or_default(),
+        data,
+    })
+}
 
 #[tauri::command]
 pub async fn upload_excel_as_blob(
-    file_name: String,
-    data: Vec<u8>,
+    request: Request<'_>,
     pool: State<'_, SqlitePool>,
 ) -> Result<String, String> {
+    let upload = read_upload(&request)?;
+    let response = import_upload(
+        pool.inner(),
+        &upload.file_name,
+        upload.data,
+        &upload.options,
+        |_| {},
+        &AtomicBool::new(false),
+    )
+    .await?;
+
+    // Serialize the JSON response to a string safely
+    serde_json::to_string(&response)
+        .map_err(|e| format!("Failed to serialize JSON response: {}", e))
+}
+
+/// Parse, check and save an upload in one transaction, reporting progress as it
+/// goes. Setting `cancelled` stops the import and rolls back everything it wrote.
+pub async fn import_upload(
+    pool: &SqlitePool,
+    file_name: &str,
+    data: &[u8],
+    options: &ImportOptions,
+    mut on_progress: impl FnMut(&ImportProgress) + Send,
+    cancelled: &AtomicBool,
+) -> Result<serde_json::Value, String> {
+    let mapping = load_mapping(pool, options.profile_id).await?;
+    // Amounts over the importing user's payment limits are rejected like any other bad cell
+    let mut conn = pool
+        .acquire()
+        .await
+        .map_err(|e| format!("Failed to connect to database: {}", e))?;
+    let options = &with_payment_limits(&mut conn, options).await?;
+    drop(conn);
+    let mut progress = ImportProgress::default();
+    on_progress(&progress);
+
+    // Parse and validate the file up front so unreadable files never create a document
+    let parsed = parse_upload_with_progress(data, file_name, &mapping, options, &mut |so_far| {
+        progress.rows_total = so_far.cheques.len();
+        progress.rows_parsed = so_far.cheques.len() + so_far.rejected_rows as usize;
+        progress.errors = so_far.issues.len();
+        on_progress(&progress);
+    })?;
+
+    // Everything below commits together; returning early drops the transaction and rolls it back
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start import transaction: {}", e))?;
+
+    let saved = save_import(&mut tx, file_name, data, &parsed, options, progress, on_progress, cancelled).await?;
+
+    check_cancelled(cancelled)?;
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit import: {}", e))?;
+
+    Ok(saved.response)
+}
+
+/// Save a parsed upload as a document and its cheques on `conn`, which the
+/// caller commits. Refuses files with rejected rows unless partial imports are
+/// allowed, and files uploaded before unless an administrator overrides the check.
+#[allow(clippy::too_many_arguments)]
+pub async fn save_import(
+    conn: &mut SqliteConnection,
+    file_name: &str,
+    data: &[u8],
+    parsed: &ParseResult,
+    options: &ImportOptions,
+    mut progress: ImportProgress,
+    mut on_progress: impl FnMut(&ImportProgress) + Send,
+    cancelled: &AtomicBool,
+) -> Result<SavedImport, String> {
+    // Without partial imports, a single bad row rejects the whole file
+    if parsed.rejected_rows > 0 && !options.allow_partial {
+        return Err(rejection_message(file_name, parsed.rejected_rows, &parsed.issues));
+    }
+
+    // A batch whose cheques do not add up to its control totals may be truncated or tampered with
+    let control_totals = check_control_totals(&parsed.declared_totals, &parsed.cheques);
+    if let Some(message) = control_totals_error(file_name, &control_totals) {
+        return Err(message);
+    }
+
+    // Check the detached signature; the policy decides whether unsigned or altered files are refused
+    let policy = load_signature_policy(&mut *conn).await?;
+    let signature = verify_file_signature(&policy, data, options.signature.as_deref(), options.signing_key.as_deref());
+    if let Some(message) = signature_policy_error(file_name, &policy, &signature) {
+        return Err(message);
+    }
+    check_cancelled(cancelled)?;
+
     // Generate the current UTC timestamp
     let created_at: chrono::DateTime<Utc> = Utc::now();
 
+    // Refuse a file that was already uploaded unless an administrator overrides the check
+    let hash = content_hash(data);
+    let duplicate = find_duplicate_document(&mut *conn, &hash).await?;
+    if let Some(earlier) = &duplicate {
+        if !options.allow_duplicate_file {
+            return Err(duplicate_file_error(file_name, earlier));
+        }
+        require_admin(&mut *conn, options.user_id, "import a duplicate file").await?;
+    }
+
     // Insert the file metadata and binary data into the database
+    let signature_status = signature.status.as_str();
+    let signature_algorithm = signature.algorithm.map(|algorithm| algorithm.as_str());
     let result = sqlx::query!(
-        "INSERT INTO documents (file_name, file_data, created_at) VALUES (?, ?, ?)",
+        "INSERT INTO documents (file_name, file_data, content_hash, created_at, signature_status, signature_key,
+                                signature_algorithm, signature, signature_detail)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
         file_name,
         data,
-        created_at
+        hash,
+        created_at,
+        signature_status,
+        signature.key_name,
+        signature_algorithm,
+        signature.signature,
+        signature.detail
     )
-    .execute(pool.inner())
+    .execute(&mut *conn)
     .await
     .map_err(|e| format!("Failed to insert blob into the documents table: {}", e))?;
 
     let document_id = result.last_insert_rowid();
 
-    // Process Excel data and insert cheques
-    let cursor = Cursor::new(data.clone());
-    if let Ok(mut workbook) = Xlsx::new(cursor) {
-        if let Ok(range) = workbook.worksheet_range("Sheet1") {
-            for row in range.rows().skip(1) {
-                if row.len() >= 3 {
-                    let cheque_number = match &row[0] {
-                        Data::String(s) => s.clone(),
-                        Data::Int(i) => i.to_string(),
-                        Data::Float(f) => f.to_string(),
-                        _ => continue,
-                    };
-                    let amount = match &row[1] {
-                        Data::Float(f) => *f,
-                        Data::Int(i) => *i as f64,
-                        _ => continue,
-                    };
-                    let client_name = match &row[2] {
-                        Data::String(s) => s.clone(),
-                        _ => continue,
-                    };
-
-                    sqlx::query!(
-                        "INSERT INTO cheques (document_id, cheque_number, amount, client_name, status) VALUES (?, ?, ?, ?, 'Pending')",
-                        document_id,
-                        cheque_number,
-                        amount,
-                        client_name
-                    )
-                    .execute(pool.inner())
-                    .await
-                    .map_err(|e| format!("Failed to insert cheque: {}", e))?;
-                }
-            }
+    // Cheques that may duplicate an existing payment, or an earlier row of this
+    // file, are imported but flagged, and cannot be approved until someone
+    // overrides the flag with a reason
+    progress.stage = ImportStage::CheckingDuplicates;
+    on_progress(&progress);
+    let rules = load_duplicate_rules(&mut *conn).await?;
+    let within_upload = find_duplicates_within_upload(&rules, &parsed.cheques);
+    let mut duplicate_flags: Vec<Option<String>> = Vec::with_capacity(parsed.cheques.len());
+    for (index, (cheque, mut matches)) in parsed.cheques.iter().zip(within_upload).enumerate() {
+        if index % INSERT_BATCH_SIZE == 0 {
+            check_cancelled(cancelled)?;
         }
+        matches.extend(find_duplicate_payments(&mut *conn, &rules, &PaymentCandidate {
+            account_number: cheque.account_number.as_deref(),
+            cheque_number: &cheque.cheque_number,
+            client_name: &cheque.client_name,
+            amount: cheque.amount,
+            amount_minor: cheque.amount_minor,
+            currency: &cheque.currency,
+            cheque_id: None,
+        })
+        .await?);
+        duplicate_flags.push((!matches.is_empty()).then(|| matches.join("; ")));
+    }
+
+    progress.stage = ImportStage::Inserting;
+    on_progress(&progress);
+    let mut cheque_ids: Vec<i64> = Vec::with_capacity(parsed.cheques.len());
+    for (cheques, flags) in parsed
+        .cheques
+        .chunks(INSERT_BATCH_SIZE)
+        .zip(duplicate_flags.chunks(INSERT_BATCH_SIZE))
+    {
+        check_cancelled(cancelled)?;
+        cheque_ids.extend(insert_cheque_batch(&mut *conn, document_id, cheques, flags).await?);
+        progress.rows_inserted += cheques.len();
+        on_progress(&progress);
     }
+
+    let flagged: Vec<serde_json::Value> = parsed
+        .cheques
+        .iter()
+        .zip(&duplicate_flags)
+        .filter_map(|(cheque, flag)| flag.as_ref().map(|flag| json!({
+            "sheet_name": cheque.sheet_name,
+            "row_number": cheque.row_number,
+            "cheque_number": cheque.cheque_number,
+            "duplicate_flag": flag
+        })))
+        .collect();
+
+    save_import_issues(&mut *conn, document_id, &parsed.issues).await?;
+
     // Create a JSON response with relevant metadata
-    let response: serde_json::Value = json!({
-        "status": "success",
+    let response = json!({
+        "status": if parsed.issues.is_empty() { "success" } else { "partial" },
         "message": format!("File '{}' saved successfully!", file_name),
         "file_name": file_name,
         "document_id": document_id,
-        "created_at": created_at.to_rfc3339()
+        "created_at": created_at.to_rfc3339(),
+        "imported_count": parsed.cheques.len(),
+        "rejected_count": parsed.rejected_rows,
+        "issues": parsed.issues,
+        "skipped_sheets": parsed.skipped_sheets,
+        "flagged_duplicates": flagged,
+        "control_totals": control_totals,
+        "signature": signature,
+        "duplicate_of": duplicate.map(|earlier| json!({
+            "document_id": earlier.id,
+            "file_name": earlier.file_name,
+            "created_at": earlier.created_at
+        }))
     });
 
-    // Serialize the JSON response to a string safely
-    serde_json::to_string(&response)
-        .map_err(|e| format!("Failed to serialize JSON response: {}", e))
+    Ok(SavedImport { document_id, cheque_ids, response })
+}
+
+/// Insert up to `INSERT_BATCH_SIZE` cheques with a single multi-row statement,
+/// returning their ids in order
+async fn insert_cheque_batch(
+    conn: &mut SqliteConnection,
+    document_id: i64,
+    cheques: &[ParsedCheque],
+    duplicate_flags: &[Option<String>],
+) -> Result<Vec<i64>, String> {
+    if cheques.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let sql = format!(
+        "INSERT INTO cheques (document_id, cheque_number, amount_minor, client_name, account_number, duplicate_flag,
+                              issue_date, date_field, memo, remarks, source_sheet, source_row,
+                              address_line1, address_line2, city, region, postal_code, country, delivery_method, currency,
+                              status)
+         VALUES {}",
+        vec!["(?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, 'Pending')"; cheques.len()].join(", ")
+    );
+    let mut query = sqlx::query(&sql);
+    for (cheque, duplicate_flag) in cheques.iter().zip(duplicate_flags) {
+        query = query
+            .bind(document_id)
+            .bind(&cheque.cheque_number)
+            .bind(cheque.amount_minor)
+            .bind(&cheque.client_name)
+            .bind(&cheque.account_number)
+            .bind(duplicate_flag)
+            .bind(&cheque.issue_date)
+            .bind(&cheque.issue_date)
+            .bind(&cheque.memo)
+            .bind(&cheque.remarks)
+            .bind(&cheque.sheet_name)
+            .bind(cheque.row_number)
+            .bind(&cheque.address.address_line1)
+            .bind(&cheque.address.address_line2)
+            .bind(&cheque.address.city)
+            .bind(&cheque.address.region)
+            .bind(&cheque.address.postal_code)
+            .bind(&cheque.address.country)
+            .bind(cheque.delivery_method.map(|method| method.as_str()))
+            .bind(&cheque.currency);
+    }
+
+    let result = query
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| format!("Failed to insert cheques: {}", e))?;
+
+    // One statement inside a write transaction assigns consecutive row ids
+    let last_id = result.last_insert_rowid();
+    let first_id = last_id - cheques.len() as i64 + 1;
+    Ok((first_id..=last_id).collect())
+}
+
+fn check_cancelled(cancelled: &AtomicBool) -> Result<(), String> {
+    if cancelled.load(Ordering::Relaxed) {
+        return Err(IMPORT_CANCELLED.to_string());
+    }
+    Ok(())
+}
+
+/// Summarise validation failures for an all-or-nothing import that was refused
+fn rejection_message(file_name: &str, rejected_rows: u32, issues: &[RowIssue]) -> String {
+    const MAX_LISTED: usize = 10;
+    let mut lines: Vec<String> = issues
+        .iter()
+        .take(MAX_LISTED)
+        .map(|issue| format!(
+            "{} row {}{}: {}",
+            issue.sheet_name,
+            issue.row_number,
+            issue.column_name.as_ref().map(|c| format!(" column {}", c)).unwrap_or_default(),
+            issue.reason
+        ))
+        .collect();
+    if issues.len() > MAX_LISTED {
+        lines.push(format!("...and {} more issue(s)", issues.len() - MAX_LISTED));
+    }
+    format!(
+        "File '{}' was not imported: {} row(s) failed validation. Enable partial import to keep the valid rows.\n{}",
+        file_name,
+        rejected_rows,
+        lines.join("\n")
+    )
 }
@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, SystemTime};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteConnection, SqlitePool};
+use tauri::{AppHandle, Emitter};
+use super::app_settings::get_setting;
+use super::control_totals::ControlTotals;
+use super::file_signatures::signature_file_text;
+use super::file_uploader::import_upload;
+use super::import_parser::ImportOptions;
+
+pub const HOT_FOLDER_KEY: &str = "hot_folder";
+/// Event emitted to the window for every file the watcher imports or rejects
+pub const HOT_FOLDER_EVENT: &str = "hot-folder-import";
+pub const MIN_POLL_SECONDS: u64 = 5;
+
+const PROCESSED_DIR: &str = "processed";
+const FAILED_DIR: &str = "failed";
+const SIDECAR_SUFFIX: &str = ".result.json";
+/// Detached signature dropped next to a payment file, e.g. `payments.xlsx.sig`
+const SIGNATURE_SUFFIX: &str = ".sig";
+const SUPPORTED_EXTENSIONS: &[&str] = &["xlsx", "xlsm", "xlsb", "xls", "ods", "csv", "tsv"];
+
+/// Watched folder that payment files are imported from automatically
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HotFolderConfig {
+    pub enabled: bool,
+    pub path: Option<String>,
+    /// Column mapping profile applied to every file; headers are auto-detected when absent
+    pub profile_id: Option<i64>,
+    pub allow_partial: bool,
+    /// Paying account for rows that do not name one
+    pub account_number: Option<String>,
+    /// Currency for rows that do not name one
+    pub currency: Option<String>,
+    /// Control totals every dropped file must match, such as an ERP trailer row
+    pub control_totals: Option<ControlTotals>,
+    pub poll_seconds: u64,
+}
+
+impl Default for HotFolderConfig {
+    fn default() -> Self {
+        HotFolderConfig {
+            enabled: false,
+            path: None,
+            profile_id: None,
+            allow_partial: false,
+            account_number: None,
+            currency: None,
+            control_totals: None,
+            poll_seconds: 30,
+        }
+    }
+}
+
+/// What happened to one file picked up from the hot folder
+#[derive(Debug, Clone, Serialize)]
+pub struct IngestionOutcome {
+    pub file_name: String,
+    /// "imported" or "failed"
+    pub status: &'static str,
+    pub moved_to: String,
+    pub document_id: Option<i64>,
+    pub error: Option<String>,
+}
+
+/// Size and modification time of a file when it was last seen. A file is only
+/// imported once it is unchanged between two scans, so half-written files are left alone.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileSnapshot {
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+pub async fn load_hot_folder_config(conn: &mut SqliteConnection) -> Result<HotFolderConfig, String> {
+    Ok(get_setting(conn, HOT_FOLDER_KEY).await?.unwrap_or_default())
+}
+
+/// Poll the configured hot folder for the lifetime of the app. The settings are
+/// re-read before every scan so changes apply without a restart.
+pub fn start_hot_folder_watcher(app: AppHandle, pool: SqlitePool) {
+    tauri::async_runtime::spawn(async move {
+        let mut seen: HashMap<PathBuf, FileSnapshot> = HashMap::new();
+        loop {
+            let config = match pool.acquire().await {
+                Ok(mut conn) => load_hot_folder_config(&mut conn).await.unwrap_or_else(|e| {
+                    println!("✗ Hot folder settings unavailable: {}", e);
+                    HotFolderConfig::default()
+                }),
+                Err(e) => {
+                    println!("✗ Hot folder could not connect to database: {}", e);
+                    HotFolderConfig::default()
+                }
+            };
+
+            match (&config.path, config.enabled) {
+                (Some(path), true) => match scan_hot_folder(&pool, Path::new(path), &config, &mut seen).await {
+                    Ok(outcomes) => {
+                        for outcome in outcomes {
+                            let _ = app.emit(HOT_FOLDER_EVENT, outcome);
+                        }
+                    }
+                    Err(e) => println!("✗ Hot folder scan failed: {}", e),
+                },
+                _ => seen.clear(),
+            }
+
+            tokio::time::sleep(Duration::from_secs(config.poll_seconds.max(MIN_POLL_SECONDS))).await;
+        }
+    });
+}
+
+/// Import every supported file in `dir` that has stopped changing since the previous scan
+pub async fn scan_hot_folder(
+    pool: &SqlitePool,
+    dir: &Path,
+    config: &HotFolderConfig,
+    seen: &mut HashMap<PathBuf, FileSnapshot>,
+) -> Result<Vec<IngestionOutcome>, String> {
+    let mut entries = tokio::fs::read_dir(dir)
+        .await
+        .map_err(|e| format!("Failed to read hot folder '{}': {}", dir.display(), e))?;
+
+    let mut current: HashMap<PathBuf, FileSnapshot> = HashMap::new();
+    let mut ready: Vec<PathBuf> = Vec::new();
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .map_err(|e| format!("Failed to read hot folder '{}': {}", dir.display(), e))?
+    {
+        let path = entry.path();
+        let Ok(metadata) = entry.metadata().await else { continue };
+        if !metadata.is_file() || !is_importable(&path) {
+            continue;
+        }
+
+        let snapshot = FileSnapshot { len: metadata.len(), modified: metadata.modified().ok() };
+        if seen.get(&path) == Some(&snapshot) {
+            ready.push(path);
+        } else {
+            current.insert(path, snapshot);
+        }
+    }
+    *seen = current;
+
+    let mut outcomes: Vec<IngestionOutcome> = Vec::new();
+    for path in ready {
+        match ingest_file(pool, dir, &path, config).await {
+            Ok(outcome) => outcomes.push(outcome),
+            // Left in place and retried once it is stable again
+            Err(e) => println!("✗ Hot folder could not process '{}': {}", path.display(), e),
+        }
+    }
+    Ok(outcomes)
+}
+
+/// Import one file the same way as an upload, then move it to `processed/` or
+/// `failed/` with a sidecar JSON file describing the result
+async fn ingest_file(
+    pool: &SqlitePool,
+    dir: &Path,
+    path: &Path,
+    config: &HotFolderConfig,
+) -> Result<IngestionOutcome, String> {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .ok_or_else(|| format!("'{}' has no file name", path.display()))?;
+    let data = tokio::fs::read(path)
+        .await
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+    let signature_path = with_suffix(path, SIGNATURE_SUFFIX);
+    let signature = tokio::fs::read(&signature_path)
+        .await
+        .ok()
+        .map(|bytes| signature_file_text(&bytes));
+
+    let options = ImportOptions {
+        profile_id: config.profile_id,
+        allow_partial: config.allow_partial,
+        account_number: config.account_number.clone(),
+        currency: config.currency.clone(),
+        control_totals: config.control_totals.clone(),
+        signature: signature.clone(),
+        ..Default::default()
+    };
+    let outcome = import_upload(pool, &file_name, &data, &options, |_| {}, &AtomicBool::new(false)).await;
+
+    let (status, target_dir) = match outcome {
+        Ok(_) => ("imported", dir.join(PROCESSED_DIR)),
+        Err(_) => ("failed", dir.join(FAILED_DIR)),
+    };
+    tokio::fs::create_dir_all(&target_dir)
+        .await
+        .map_err(|e| format!("Failed to create '{}': {}", target_dir.display(), e))?;
+
+    let destination = unique_destination(&target_dir, &file_name).await;
+    tokio::fs::rename(path, &destination)
+        .await
+        .map_err(|e| format!("Failed to move file to '{}': {}", destination.display(), e))?;
+    if signature.is_some() {
+        let signature_destination = with_suffix(&destination, SIGNATURE_SUFFIX);
+        tokio::fs::rename(&signature_path, &signature_destination)
+            .await
+            .map_err(|e| format!("Failed to move signature to '{}': {}", signature_destination.display(), e))?;
+    }
+
+    let (result, error) = match outcome {
+        Ok(response) => (Some(response), None),
+        Err(e) => (None, Some(e)),
+    };
+    let sidecar = serde_json::json!({
+        "file_name": file_name,
+        "status": status,
+        "processed_at": Utc::now().to_rfc3339(),
+        "result": result,
+        "error": error
+    });
+    let sidecar_path = with_suffix(&destination, SIDECAR_SUFFIX);
+    let sidecar_json = serde_json::to_vec_pretty(&sidecar)
+        .map_err(|e| format!("Failed to serialize result file: {}", e))?;
+    tokio::fs::write(&sidecar_path, sidecar_json)
+        .await
+        .map_err(|e| format!("Failed to write result file: {}", e))?;
+
+    Ok(IngestionOutcome {
+        file_name,
+        status,
+        moved_to: destination.to_string_lossy().into_owned(),
+        document_id: result.as_ref().and_then(|r| r["document_id"].as_i64()),
+        error,
+    })
+}
+
+/// Supported spreadsheet files, ignoring hidden files, Office lock files and sidecars
+fn is_importable(path: &Path) -> bool {
+    let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+        return false;
+    };
+    if name.starts_with('.') || name.starts_with("~$") || name.ends_with(SIDECAR_SUFFIX) {
+        return false;
+    }
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| SUPPORTED_EXTENSIONS.iter().any(|e| ext.eq_ignore_ascii_case(e)))
+        .unwrap_or(false)
+}
+
+/// `path` with `suffix` appended to its file name
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_os_string();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// Path for `file_name` in `dir`, adding a timestamp when a file of that name was already moved there
+async fn unique_destination(dir: &Path, file_name: &str) -> PathBuf {
+    let candidate = dir.join(file_name);
+    if !tokio::fs::try_exists(&candidate).await.unwrap_or(false) {
+        return candidate;
+    }
+
+    let (stem, extension) = match file_name.rsplit_once('.') {
+        Some((stem, ext)) => (stem, format!(".{}", ext)),
+        None => (file_name, String::new()),
+    };
+    let stamp = Utc::now().format("%Y%m%d-%H%M%S");
+    let mut attempt = 1;
+    loop {
+        let candidate = dir.join(format!("{}-{}-{}{}", stem, stamp, attempt, extension));
+        if !tokio::fs::try_exists(&candidate).await.unwrap_or(false) {
+            return candidate;
+        }
+        attempt += 1;
+    }
+}
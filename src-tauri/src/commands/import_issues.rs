@@ -0,0 +1,27 @@
+use sqlx::SqliteConnection;
+use super::import_parser::RowIssue;
+
+/// Store the issues found while importing `document_id`
+pub async fn save_import_issues(
+    conn: &mut SqliteConnection,
+    document_id: i64,
+    issues: &[RowIssue],
+) -> Result<(), String> {
+    for issue in issues {
+        sqlx::query(
+            "INSERT INTO import_issues (document_id, sheet_name, row_number, column_name, field, reason, cell_value)
+             VALUES (?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(document_id)
+        .bind(&issue.sheet_name)
+        .bind(issue.row_number)
+        .bind(&issue.column_name)
+        .bind(&issue.field)
+        .bind(&issue.reason)
+        .bind(&issue.cell_value)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| format!("Failed to record import issue: {}", e))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use tauri::ipc::Request;
+use tauri::{AppHandle, Emitter, Manager, State};
+use uuid::Uuid;
+use super::file_uploader::{import_upload, read_upload, ImportProgress, IMPORT_CANCELLED};
+
+/// Event emitted to the window as a background import advances
+pub const IMPORT_PROGRESS_EVENT: &str = "import-progress";
+/// Event emitted once when a background import completes, fails or is cancelled
+pub const IMPORT_FINISHED_EVENT: &str = "import-finished";
+
+/// Cancellation flags of the background imports currently running, by job id
+#[derive(Default)]
+pub struct ImportJobs {
+    running: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+impl ImportJobs {
+    fn register(&self, job_id: &str) -> Result<Arc<AtomicBool>, String> {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.running
+            .lock()
+            .map_err(|_| "Import job registry is unavailable".to_string())?
+            .insert(job_id.to_string(), cancelled.clone());
+        Ok(cancelled)
+    }
+
+    fn finish(&self, job_id: &str) {
+        if let Ok(mut running) = self.running.lock() {
+            running.remove(job_id);
+        }
+    }
+
+    fn cancel(&self, job_id: &str) -> Result<(), String> {
+        let running = self
+            .running
+            .lock()
+            .map_err(|_| "Import job registry is unavailable".to_string())?;
+        let cancelled = running
+            .get(job_id)
+            .ok_or_else(|| format!("No running import with ID {}", job_id))?;
+        cancelled.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct ProgressEvent<'a> {
+    job_id: &'a str,
+    file_name: &'a str,
+    #[serde(flatten)]
+    progress: &'a ImportProgress,
+}
+
+#[derive(Clone, Serialize)]
+struct FinishedEvent<'a> {
+    job_id: &'a str,
+    file_name: &'a str,
+    /// "completed", "failed" or "cancelled"
+    status: &'static str,
+    /// Same response `upload_excel_as_blob` returns, when the import completed
+    result: Option<serde_json::Value>,
+    error: Option<String>,
+}
+
+/// Import an upload in the background and return its job id straight away.
+/// Progress arrives as `import-progress` events and the outcome as one `import-finished` event.
+/// The file is the raw request body, named by the `import-upload` header.
+#[tauri::command]
+pub async fn start_import_job(
+    app: AppHandle,
+    request: Request<'_>,
+    pool: State<'_, SqlitePool>,
+    jobs: State<'_, ImportJobs>,
+) -> Result<String, String> {
+    let upload = read_upload(&request)?;
+    let file_name = upload.file_name;
+    let options = upload.options;
+    let data = upload.data.to_vec();
+    let job_id = Uuid::new_v4().to_string();
+    let cancelled = jobs.register(&job_id)?;
+    let pool = pool.inner().clone();
+    let id = job_id.clone();
+
+    tauri::async_runtime::spawn(async move {
+        let outcome = import_upload(
+            &pool,
+            &file_name,
+            &data,
+            &options,
+            |progress| {
+                let _ = app.emit(IMPORT_PROGRESS_EVENT, ProgressEvent {
+                    job_id: &id,
+                    file_name: &file_name,
+                    progress,
+                });
+            },
+            &cancelled,
+        )
+        .await;
+        app.state::<ImportJobs>().finish(&id);
+
+        let (status, result, error) = match outcome {
+            Ok(response) => ("completed", Some(response), None),
+            Err(_) if cancelled.load(Ordering::Relaxed) => ("cancelled", None, Some(IMPORT_CANCELLED.to_string())),
+            Err(e) => ("failed", None, Some(e)),
+        };
+        let _ = app.emit(IMPORT_FINISHED_EVENT, FinishedEvent {
+            job_id: &id,
+            file_name: &file_name,
+            status,
+            result,
+            error,
+        });
+    });
+
+    Ok(job_id)
+}
+
+/// Ask a running background import to stop; nothing it wrote is kept
+#[tauri::command]
+pub async fn cancel_import_job(job_id: String, jobs: State<'_, ImportJobs>) -> Result<(), String> {
+    jobs.cancel(&job_id)
+}
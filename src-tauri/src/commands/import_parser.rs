@@ -0,0 +1,557 @@
+use std::collections::HashMap;
+use calamine::{Data, DataType, ExcelDateTime, ExcelDateTimeType, Range};
+use chrono::{NaiveDate, NaiveDateTime};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use super::amount_parser::{decimal_from_float, parse_amount_text, DecimalSeparator};
+use super::column_mapping::{column_index_to_letters, ChequeField, ColumnMapping, ResolvedColumns};
+use super::control_totals::{
+    entered_totals, find_trailer_row, read_named_totals, read_trailer_totals, ControlTotals, DeclaredTotals,
+};
+use super::currency::{default_currency, find_currency, parse_currency};
+use super::encrypted_workbook::unlock_upload;
+use super::payee_delivery::{DeliveryMethod, PayeeAddress, REQUIRED_ADDRESS_FIELDS};
+use super::payment_limits::UserPaymentLimits;
+use super::spreadsheet::{read_sheets, sheet_names};
+
+/// Options shared by the import preview and the persisted upload
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ImportOptions {
+    /// Column mapping profile; headers are auto-detected when absent
+    pub profile_id: Option<i64>,
+    /// Commit the valid rows and record the rejected ones instead of refusing the file
+    pub allow_partial: bool,
+    /// Import even if an identical file was uploaded before (administrators only)
+    pub allow_duplicate_file: bool,
+    /// User performing the import, checked when a safeguard is overridden
+    pub user_id: Option<i64>,
+    /// Paying account for rows that do not name one
+    pub account_number: Option<String>,
+    /// Sheets to import; falls back to the profile's sheet, then the first sheet
+    pub sheet_names: Option<Vec<String>>,
+    /// Import every sheet, skipping those without recognisable cheque columns
+    pub all_sheets: bool,
+    /// Verify the imported cheques against totals from the file or the user
+    pub control_totals: Option<ControlTotals>,
+    /// Detached signature over the file bytes, written in the signature policy's
+    /// encoding or prefixed with "base64:" or "hex:"
+    pub signature: Option<String>,
+    /// Trusted key the file claims to be signed with; any trusted key is tried when absent
+    pub signing_key: Option<String>,
+    /// Currency of rows that do not name one; US dollars when absent
+    pub currency: Option<String>,
+    /// Password of an encrypted workbook. Used only to decrypt the file in
+    /// memory; it is never serialized, so staged imports do not store it.
+    #[serde(skip_serializing)]
+    pub password: Option<String>,
+    /// Payment limits of the importing user, loaded from the settings before the
+    /// rows are validated. Never serialized, so staged rows are checked against
+    /// the limits in force when they are corrected and committed.
+    #[serde(skip)]
+    pub payment_limits: Option<UserPaymentLimits>,
+}
+
+/// Problem found with a cell while importing, located the way a user sees it in the sheet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RowIssue {
+    pub sheet_name: String,
+    /// 1-based spreadsheet row number
+    pub row_number: u32,
+    pub column_name: Option<String>,
+    pub field: Option<String>,
+    pub reason: String,
+    pub cell_value: Option<String>,
+}
+
+/// Cheque read from one valid spreadsheet row, exactly as it will be saved
+#[derive(Debug, Clone, Serialize)]
+pub struct ParsedCheque {
+    pub sheet_name: String,
+    /// 1-based spreadsheet row number
+    pub row_number: u32,
+    pub cheque_number: String,
+    pub amount: Decimal,
+    /// `amount` in the currency's minor units, as it is stored
+    pub amount_minor: i64,
+    pub client_name: String,
+    pub account_number: Option<String>,
+    /// Cheque date as `YYYY-MM-DD`
+    pub issue_date: Option<String>,
+    pub memo: Option<String>,
+    pub remarks: Option<String>,
+    #[serde(flatten)]
+    pub address: PayeeAddress,
+    pub delivery_method: Option<DeliveryMethod>,
+    /// ISO 4217 currency code
+    pub currency: String,
+}
+
+/// Text date layouts accepted in date columns. Slash, dash and dot dates are
+/// read day-first, the way they are written on cheques.
+const DATE_FORMATS: &[&str] = &[
+    "%Y-%m-%d", "%Y/%m/%d", "%d/%m/%Y", "%d-%m-%Y", "%d.%m.%Y",
+    "%d %b %Y", "%d %B %Y", "%d-%b-%Y", "%b %d, %Y", "%B %d, %Y",
+];
+const DATETIME_FORMATS: &[&str] = &["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"];
+/// Largest serial Excel can display as a date (31 December 9999)
+const MAX_DATE_SERIAL: f64 = 2_958_465.0;
+/// Whole numbers above this lose digits as spreadsheet floats
+const MAX_EXACT_NUMBER: f64 = 1e15;
+
+/// Outcome of parsing an upload: the cheques to save plus every rejected cell
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ParseResult {
+    pub cheques: Vec<ParsedCheque>,
+    pub issues: Vec<RowIssue>,
+    pub rejected_rows: u32,
+    /// Sheets left out of an all-sheets import, with the reason
+    pub skipped_sheets: Vec<SkippedSheet>,
+    /// Control totals the imported cheques must match
+    pub declared_totals: Vec<DeclaredTotals>,
+}
+
+/// What scanning the selected sheets found besides their data rows
+#[derive(Debug, Clone, Default)]
+pub struct UploadScan {
+    pub skipped_sheets: Vec<SkippedSheet>,
+    pub declared_totals: Vec<DeclaredTotals>,
+}
+
+/// Sheet ignored during an all-sheets import
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkippedSheet {
+    pub sheet_name: String,
+    pub reason: String,
+}
+
+/// Mapped cells of one non-blank spreadsheet row, before validation
+#[derive(Debug, Clone)]
+pub struct RowCells {
+    pub sheet_name: String,
+    /// 1-based spreadsheet row number
+    pub row_number: u32,
+    /// 0-based column each field was read from
+    pub columns: HashMap<ChequeField, u32>,
+    /// Non-empty cells by field
+    pub values: HashMap<ChequeField, Data>,
+}
+
+impl RowCells {
+    /// Mapped cells of the row at absolute index `row`; `None` when every one is
+    /// empty, since such rows are padding rather than cheques
+    fn read(range: &Range<Data>, columns: &ResolvedColumns, row: u32, sheet_name: &str) -> Option<RowCells> {
+        let values: HashMap<ChequeField, Data> = ChequeField::ALL
+            .iter()
+            .filter_map(|field| match columns.cell(range, row, *field) {
+                None | Some(Data::Empty) => None,
+                Some(cell) => Some((*field, cell.clone())),
+            })
+            .collect();
+        (!values.is_empty()).then(|| RowCells {
+            sheet_name: sheet_name.to_string(),
+            row_number: row + 1,
+            columns: columns.columns.clone(),
+            values,
+        })
+    }
+}
+
+/// Parse an uploaded workbook, CSV or TSV file with `mapping`. The preview and
+/// the persisted import both go through here so they always agree.
+pub fn parse_upload(
+    data: &[u8],
+    file_name: &str,
+    mapping: &ColumnMapping,
+    options: &ImportOptions,
+) -> Result<ParseResult, String> {
+    parse_upload_with_progress(data, file_name, mapping, options, &mut |_| {})
+}
+
+/// Rows parsed between progress callbacks on large sheets
+const PROGRESS_INTERVAL: u32 = 1000;
+
+/// `parse_upload`, calling `on_progress` with the result so far every
+/// `PROGRESS_INTERVAL` rows and once parsing finishes
+pub fn parse_upload_with_progress(
+    data: &[u8],
+    file_name: &str,
+    mapping: &ColumnMapping,
+    options: &ImportOptions,
+    on_progress: &mut dyn FnMut(&ParseResult),
+) -> Result<ParseResult, String> {
+    let mut result = ParseResult::default();
+    let mut rows_seen: u32 = 0;
+    let scan = visit_rows(data, file_name, mapping, options, &mut |cells| {
+        match validate_cells(&cells, mapping, options) {
+            Ok(cheque) => result.cheques.push(cheque),
+            Err(row_issues) => {
+                result.rejected_rows += 1;
+                result.issues.extend(row_issues);
+            }
+        }
+        rows_seen += 1;
+        if rows_seen.is_multiple_of(PROGRESS_INTERVAL) {
+            on_progress(&result);
+        }
+    })?;
+    result.skipped_sheets = scan.skipped_sheets;
+    result.declared_totals = scan.declared_totals;
+    on_progress(&result);
+    Ok(result)
+}
+
+/// Read the mapped cells of every non-blank row without validating them, so
+/// rows can be staged and corrected before they are imported
+pub fn read_upload_rows(
+    data: &[u8],
+    file_name: &str,
+    mapping: &ColumnMapping,
+    options: &ImportOptions,
+) -> Result<(Vec<RowCells>, UploadScan), String> {
+    let mut rows: Vec<RowCells> = Vec::new();
+    let scan = visit_rows(data, file_name, mapping, options, &mut |cells| rows.push(cells))?;
+    Ok((rows, scan))
+}
+
+/// Call `visit` with each non-blank data row of the selected sheets, returning
+/// the sheets an all-sheets import had to skip and the control totals declared.
+/// Trailer rows hold totals, so they are never visited as cheques.
+fn visit_rows(
+    data: &[u8],
+    file_name: &str,
+    mapping: &ColumnMapping,
+    options: &ImportOptions,
+    visit: &mut dyn FnMut(RowCells),
+) -> Result<UploadScan, String> {
+    if let Some(code) = &options.currency {
+        parse_currency(code)?;
+    }
+    let data = &*unlock_upload(data, file_name, options.password.as_deref())?;
+    let available = sheet_names(data, file_name)?;
+    let targets = select_sheets(&available, mapping, options)?;
+
+    let mut scan = UploadScan::default();
+    for (sheet_name, range) in read_sheets(data, file_name, &targets)? {
+        let columns = match mapping.resolve(&range, &sheet_name) {
+            Ok(columns) => columns,
+            Err(reason) if options.all_sheets => {
+                scan.skipped_sheets.push(SkippedSheet { sheet_name, reason });
+                continue;
+            }
+            Err(reason) => return Err(reason),
+        };
+
+        let mut rows = columns.data_rows(&range);
+        if let Some(ControlTotals::TrailerRow { amount_column, count_column }) = &options.control_totals {
+            let trailer = find_trailer_row(&range, rows.clone())
+                .ok_or_else(|| format!("Sheet '{}' has no trailer row with control totals", sheet_name))?;
+            scan.declared_totals.push(read_trailer_totals(
+                &range,
+                &columns,
+                trailer,
+                &sheet_name,
+                amount_column.as_deref(),
+                count_column.as_deref(),
+                mapping.decimal_separator,
+            )?);
+            rows.end = trailer;
+        }
+
+        for row in rows {
+            if let Some(cells) = RowCells::read(&range, &columns, row, &sheet_name) {
+                visit(cells);
+            }
+        }
+    }
+
+    match &options.control_totals {
+        Some(ControlTotals::NamedCells { amount_cell, count_cell }) => {
+            scan.declared_totals.push(read_named_totals(
+                data,
+                file_name,
+                amount_cell,
+                count_cell.as_deref(),
+                mapping.decimal_separator,
+            )?);
+        }
+        Some(ControlTotals::Entered { total_amount, row_count }) => {
+            scan.declared_totals.push(entered_totals(*total_amount, *row_count));
+        }
+        Some(ControlTotals::TrailerRow { .. }) | None => {}
+    }
+    Ok(scan)
+}
+
+/// Work out which sheets to import, checking that every requested sheet exists
+fn select_sheets(
+    available: &[String],
+    mapping: &ColumnMapping,
+    options: &ImportOptions,
+) -> Result<Vec<String>, String> {
+    if options.all_sheets {
+        return Ok(available.to_vec());
+    }
+
+    let requested: Vec<String> = match (&options.sheet_names, &mapping.sheet_name) {
+        (Some(names), _) if !names.is_empty() => names.clone(),
+        (Some(_), _) => return Err("Select at least one sheet to import".to_string()),
+        (None, Some(name)) => vec![name.clone()],
+        (None, None) => available.iter().take(1).cloned().collect(),
+    };
+
+    if requested.is_empty() {
+        return Err("The file does not contain any sheets".to_string());
+    }
+    if let Some(missing) = requested.iter().find(|name| !available.contains(name)) {
+        return Err(format!(
+            "Sheet '{}' not found. Available sheets: {}",
+            missing,
+            available.join(", ")
+        ));
+    }
+    Ok(requested)
+}
+
+/// Validate the mapped cells of one row, reporting every bad cell
+pub fn validate_cells(
+    cells: &RowCells,
+    mapping: &ColumnMapping,
+    options: &ImportOptions,
+) -> Result<ParsedCheque, Vec<RowIssue>> {
+    let mut reader = RowReader { cells, issues: Vec::new() };
+
+    let cheque_number = reader.required(ChequeField::ChequeNumber, |cell| {
+        parse_identifier(cell, mapping.cheque_number_digits)
+    });
+    let amount = reader.required(ChequeField::Amount, |cell| parse_amount(cell, mapping.decimal_separator));
+    let client_name = reader.required(ChequeField::ClientName, |cell| match cell {
+        Data::String(s) => Ok(s.trim().to_string()),
+        _ => Err("Client name must be text".to_string()),
+    });
+    let account_number = reader
+        .optional(ChequeField::AccountNumber, |cell| parse_identifier(cell, None))
+        .flatten()
+        .or_else(|| options.account_number.clone());
+    let issue_date = reader.optional(ChequeField::IssueDate, parse_date).flatten();
+    let memo = reader.optional(ChequeField::Memo, parse_text).flatten();
+    let remarks = reader.optional(ChequeField::Remarks, parse_text).flatten();
+    let address = PayeeAddress {
+        address_line1: reader.optional(ChequeField::AddressLine1, parse_text).flatten(),
+        address_line2: reader.optional(ChequeField::AddressLine2, parse_text).flatten(),
+        city: reader.optional(ChequeField::City, parse_text).flatten(),
+        region: reader.optional(ChequeField::Region, parse_text).flatten(),
+        postal_code: reader.optional(ChequeField::PostalCode, |cell| parse_identifier(cell, None)).flatten(),
+        country: reader.optional(ChequeField::Country, parse_text).flatten(),
+    };
+    let delivery_method = reader
+        .optional(ChequeField::DeliveryMethod, |cell| match cell {
+            Data::String(s) => s.parse::<DeliveryMethod>(),
+            other => Err(format!("Unsupported value '{}' for a delivery method", other)),
+        })
+        .flatten();
+    if let Some(method) = delivery_method.filter(|m| m.requires_address()) {
+        for field in REQUIRED_ADDRESS_FIELDS {
+            if reader.is_blank_cell(field) {
+                reader.report(field, format!("Required when the delivery method is {}", method.as_str()));
+            }
+        }
+    }
+
+    let currency = reader
+        .optional(ChequeField::Currency, |cell| match cell {
+            Data::String(s) => parse_currency(s),
+            other => Err(format!("Unsupported value '{}' for a currency code", other)),
+        })
+        .flatten()
+        .or_else(|| options.currency.as_deref().and_then(find_currency))
+        .unwrap_or_else(default_currency);
+    let amount_minor = amount.and_then(|amount| match currency.to_minor_units(amount) {
+        Ok(minor) => Some(minor),
+        Err(reason) => {
+            reader.report(ChequeField::Amount, reason);
+            None
+        }
+    });
+    if let (Some(limits), Some(amount), Some(_)) = (&options.payment_limits, amount, amount_minor) {
+        if let Err(reason) = limits.check(amount, currency, account_number.as_deref()) {
+            reader.report(ChequeField::Amount, reason);
+        }
+    }
+
+    match (cheque_number, amount, amount_minor, client_name) {
+        (Some(cheque_number), Some(amount), Some(amount_minor), Some(client_name)) if reader.issues.is_empty() => {
+            Ok(ParsedCheque {
+                sheet_name: cells.sheet_name.clone(),
+                row_number: cells.row_number,
+                cheque_number,
+                amount,
+                amount_minor,
+                client_name,
+                account_number,
+                issue_date,
+                memo,
+                remarks,
+                address,
+                delivery_method,
+                currency: currency.code.to_string(),
+            })
+        }
+        _ => Err(reader.issues),
+    }
+}
+
+/// Reads the mapped cells of a single row, collecting an issue for every unusable cell
+struct RowReader<'a> {
+    cells: &'a RowCells,
+    issues: Vec<RowIssue>,
+}
+
+impl RowReader<'_> {
+    fn cell(&self, field: ChequeField) -> &Data {
+        self.cells.values.get(&field).unwrap_or(&Data::Empty)
+    }
+
+    /// Empty, whitespace-only or unmapped
+    fn is_blank_cell(&self, field: ChequeField) -> bool {
+        match self.cell(field) {
+            Data::Empty => true,
+            Data::String(s) => s.trim().is_empty(),
+            _ => false,
+        }
+    }
+
+    /// Parse a field that every row must have
+    fn required<T>(&mut self, field: ChequeField, parse: impl Fn(&Data) -> Result<T, String>) -> Option<T> {
+        let cell = self.cell(field);
+        match check_common(cell).and_then(|_| parse(cell)) {
+            Ok(value) => Some(value),
+            Err(reason) => {
+                self.report(field, reason);
+                None
+            }
+        }
+    }
+
+    /// Parse a field that may be left blank. `Some(None)` means blank, `None` means invalid.
+    fn optional<T>(&mut self, field: ChequeField, parse: impl Fn(&Data) -> Result<T, String>) -> Option<Option<T>> {
+        match self.cell(field) {
+            Data::Empty => Some(None),
+            Data::String(s) if s.trim().is_empty() => Some(None),
+            _ => self.required(field, parse).map(Some),
+        }
+    }
+
+    fn report(&mut self, field: ChequeField, reason: String) {
+        let cell = self.cell(field);
+        let cell_value = (*cell != Data::Empty).then(|| cell.to_string());
+        self.issues.push(RowIssue {
+            sheet_name: self.cells.sheet_name.clone(),
+            row_number: self.cells.row_number,
+            column_name: self.cells.columns.get(&field).map(|c| column_index_to_letters(*c)),
+            field: Some(field.as_str().to_string()),
+            reason,
+            cell_value,
+        });
+    }
+}
+
+/// Cheque amounts must be positive. Text amounts are read with the profile's decimal separator.
+fn parse_amount(cell: &Data, separator: DecimalSeparator) -> Result<Decimal, String> {
+    let amount = match cell {
+        Data::Float(f) => decimal_from_float(*f)?,
+        Data::Int(i) => Decimal::from(*i),
+        Data::String(s) => parse_amount_text(s, separator)?,
+        other => return Err(format!("Amount '{}' is not a number", other)),
+    };
+    if amount <= Decimal::ZERO {
+        return Err(format!("Amount {} must be greater than zero", amount));
+    }
+    Ok(amount)
+}
+
+/// Identifiers such as cheque and account numbers. Text is kept exactly as
+/// written so leading zeros survive; numeric cells are written out in full
+/// rather than in exponent form. Numbers, and text of digits only, are
+/// zero-padded to `digits` when set.
+fn parse_identifier(cell: &Data, digits: Option<u32>) -> Result<String, String> {
+    let number = match cell {
+        Data::String(s) if s.trim().chars().all(|c| c.is_ascii_digit()) => s.trim().to_string(),
+        Data::String(s) => return Ok(s.trim().to_string()),
+        Data::Int(i) if *i >= 0 => i.to_string(),
+        Data::Float(f) if *f >= 0.0 && f.fract() == 0.0 && *f < MAX_EXACT_NUMBER => format!("{:.0}", f),
+        Data::Float(f) if *f >= MAX_EXACT_NUMBER => {
+            return Err(format!(
+                "Number {} is too long to be stored exactly; format the column as text",
+                cell
+            ));
+        }
+        Data::Int(_) | Data::Float(_) => return Err(format!("'{}' is not a valid identifier number", cell)),
+        other => return Err(format!("Unsupported value '{}' for an identifier", other)),
+    };
+    Ok(match digits {
+        Some(width) => format!("{:0>width$}", number, width = width as usize),
+        None => number,
+    })
+}
+
+/// Free text such as a memo or reference; numeric references are kept as written
+fn parse_text(cell: &Data) -> Result<String, String> {
+    match cell {
+        Data::String(s) => Ok(s.trim().to_string()),
+        Data::Int(i) => Ok(i.to_string()),
+        Data::Float(f) => Ok(f.to_string()),
+        other => Err(format!("Unsupported value '{}' for a text field", other)),
+    }
+}
+
+/// Read a date cell as `YYYY-MM-DD`. Accepts Excel date cells, ISO dates from
+/// ODS files, bare date serials and the text layouts in `DATE_FORMATS`.
+fn parse_date(cell: &Data) -> Result<String, String> {
+    let date = match cell {
+        Data::DateTime(dt) if dt.is_duration() => {
+            return Err(format!("'{}' is a duration, not a date", cell));
+        }
+        Data::DateTime(dt) => dt.as_datetime().map(|d| d.date()),
+        Data::DateTimeIso(s) => parse_date_text(s.trim()),
+        // Text files hold serials as digits
+        Data::String(s) => parse_date_text(s.trim())
+            .or_else(|| s.trim().parse::<u32>().ok().and_then(|serial| date_from_serial(serial as f64))),
+        Data::Int(_) | Data::Float(_) => cell.as_f64().and_then(date_from_serial),
+        _ => None,
+    };
+    date.map(|d| d.format("%Y-%m-%d").to_string())
+        .ok_or_else(|| format!("'{}' is not a recognised date", cell))
+}
+
+fn parse_date_text(text: &str) -> Option<NaiveDate> {
+    DATE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(text, format).ok())
+        .or_else(|| {
+            DATETIME_FORMATS
+                .iter()
+                .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+                .map(|d| d.date())
+        })
+}
+
+/// Convert a 1900-system Excel serial from a cell without a date format
+fn date_from_serial(serial: f64) -> Option<NaiveDate> {
+    if !(1.0..=MAX_DATE_SERIAL).contains(&serial) {
+        return None;
+    }
+    ExcelDateTime::new(serial, ExcelDateTimeType::DateTime, false)
+        .as_datetime()
+        .map(|d| d.date())
+}
+
+/// Checks shared by every field: the cell must hold a value and not a formula error
+fn check_common(cell: &Data) -> Result<(), String> {
+    match cell {
+        Data::Empty => Err("Cell is empty".to_string()),
+        Data::String(s) if s.trim().is_empty() => Err("Cell is empty".to_string()),
+        Data::Error(e) => Err(format!("Formula error {}", e)),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,581 @@
+use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
+use calamine::{CellErrorType, Data, ExcelDateTime, ExcelDateTimeType};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{Row, SqliteConnection, SqlitePool};
+use tauri::State;
+use crate::database::models::{StagedImport, StagedRow};
+use super::column_mapping::{column_index_to_letters, column_letters_to_index, load_mapping, ChequeField, ColumnMapping};
+use super::control_totals::{check_control_totals, DeclaredTotals};
+use super::document_hash::{content_hash, duplicate_file_error, find_duplicate_document};
+use super::file_uploader::{save_import, ImportProgress};
+use super::import_parser::{read_upload_rows, validate_cells, ImportOptions, ParseResult, RowCells, SkippedSheet};
+use super::payment_limits::with_payment_limits;
+use super::permissions::require_admin;
+
+/// Status of an upload whose rows can still be corrected, deleted and committed
+const STAGED: &str = "staged";
+
+/// Formula errors a staged cell can hold, matched by their displayed text
+const CELL_ERRORS: [CellErrorType; 8] = [
+    CellErrorType::Div0,
+    CellErrorType::NA,
+    CellErrorType::Name,
+    CellErrorType::Null,
+    CellErrorType::Num,
+    CellErrorType::Ref,
+    CellErrorType::Value,
+    CellErrorType::GettingData,
+];
+
+/// Cell value of a staged row, stored as JSON such as `{"type":"float","value":125.5}`.
+/// Corrections made by hand are always text and are read like text cells.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum StagedValue {
+    Text(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    /// Date cell as `YYYY-MM-DDTHH:MM:SS`
+    DateTime(String),
+    /// Duration cell as a number of days
+    Duration(f64),
+    /// Formula error such as `#DIV/0!`
+    Error(String),
+}
+
+impl StagedValue {
+    fn from_cell(cell: &Data) -> Option<StagedValue> {
+        Some(match cell {
+            Data::Empty => return None,
+            Data::String(s) => StagedValue::Text(s.clone()),
+            Data::Int(i) => StagedValue::Int(*i),
+            Data::Float(f) => StagedValue::Float(*f),
+            Data::Bool(b) => StagedValue::Bool(*b),
+            Data::DateTime(dt) if dt.is_duration() => StagedValue::Duration(dt.as_f64()),
+            Data::DateTime(dt) => match dt.as_datetime() {
+                Some(datetime) => StagedValue::DateTime(datetime.format("%Y-%m-%dT%H:%M:%S").to_string()),
+                None => StagedValue::Float(dt.as_f64()),
+            },
+            Data::DateTimeIso(s) => StagedValue::DateTime(s.clone()),
+            Data::DurationIso(s) => StagedValue::Text(s.clone()),
+            Data::Error(e) => StagedValue::Error(e.to_string()),
+        })
+    }
+
+    fn to_cell(&self) -> Data {
+        match self {
+            StagedValue::Text(s) => Data::String(s.clone()),
+            StagedValue::Int(i) => Data::Int(*i),
+            StagedValue::Float(f) => Data::Float(*f),
+            StagedValue::Bool(b) => Data::Bool(*b),
+            StagedValue::DateTime(s) => Data::DateTimeIso(s.clone()),
+            StagedValue::Duration(days) => Data::DateTime(ExcelDateTime::new(*days, ExcelDateTimeType::TimeDelta, false)),
+            StagedValue::Error(text) => CELL_ERRORS
+                .iter()
+                .find(|e| e.to_string() == *text)
+                .map(|e| Data::Error(e.clone()))
+                .unwrap_or_else(|| Data::String(text.clone())),
+        }
+    }
+}
+
+/// Staged upload settings needed to revalidate its rows
+struct StagedBatch {
+    file_name: String,
+    mapping: ColumnMapping,
+    options: ImportOptions,
+    skipped_sheets: Vec<SkippedSheet>,
+    declared_totals: Vec<DeclaredTotals>,
+}
+
+/// Read an upload into the staging area without creating any cheques. Every
+/// non-blank row is kept, valid or not, so it can be reviewed and corrected first.
+/// A file that was already uploaded or is staged already is refused up front.
+#[tauri::command]
+pub async fn stage_import(
+    file_name: String,
+    data: Vec<u8>,
+    options: Option<ImportOptions>,
+    pool: State<'_, SqlitePool>,
+) -> Result<String, String> {
+    let options = options.unwrap_or_default();
+    let mapping = load_mapping(pool.inner(), options.profile_id).await?;
+    let (rows, scan) = read_upload_rows(&data, &file_name, &mapping, &options)?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start staging transaction: {}", e))?;
+
+    // Refuse a file that was already uploaded, or is already waiting for review,
+    // before anyone spends time correcting it
+    let hash = content_hash(&data);
+    if let Some(earlier) = find_duplicate_document(&mut tx, &hash).await? {
+        if !options.allow_duplicate_file {
+            return Err(duplicate_file_error(&file_name, &earlier));
+        }
+        require_admin(&mut tx, options.user_id, "import a duplicate file").await?;
+    }
+    let staged: Option<(i64, String)> = sqlx::query_as(
+        "SELECT id, file_name FROM staged_imports WHERE content_hash = ? AND status = 'staged' ORDER BY id ASC LIMIT 1"
+    )
+    .bind(&hash)
+    .fetch_optional(&mut tx)
+    .await
+    .map_err(|e| format!("Failed to check for staged duplicates: {}", e))?;
+    if let Some((staged_import_id, staged_name)) = staged {
+        return Err(format!(
+            "File '{}' is identical to '{}', already staged for review as staged import {}. Commit or discard it first.",
+            file_name, staged_name, staged_import_id
+        ));
+    }
+
+    let result = sqlx::query(
+        "INSERT INTO staged_imports (file_name, file_data, content_hash, mapping, options, skipped_sheets,
+                                     declared_totals, created_by)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(&file_name)
+    .bind(&data)
+    .bind(&hash)
+    .bind(to_json(&mapping)?)
+    .bind(to_json(&options)?)
+    .bind(to_json(&scan.skipped_sheets)?)
+    .bind(to_json(&scan.declared_totals)?)
+    .bind(options.user_id)
+    .execute(&mut tx)
+    .await
+    .map_err(|e| format!("Failed to stage '{}': {}", file_name, e))?;
+    let staged_import_id = result.last_insert_rowid();
+
+    let limited_options = with_payment_limits(&mut tx, &options).await?;
+    let mut valid_cheques = Vec::new();
+    for cells in &rows {
+        let issues = match validate_cells(cells, &mapping, &limited_options) {
+            Ok(cheque) => {
+                valid_cheques.push(cheque);
+                None
+            }
+            Err(issues) => Some(issues),
+        };
+
+        let columns: HashMap<ChequeField, String> = cells
+            .columns
+            .iter()
+            .map(|(field, column)| (*field, column_index_to_letters(*column)))
+            .collect();
+        let values: HashMap<ChequeField, StagedValue> = cells
+            .values
+            .iter()
+            .filter_map(|(field, cell)| StagedValue::from_cell(cell).map(|value| (*field, value)))
+            .collect();
+        let values_json = to_json(&values)?;
+
+        sqlx::query(
+            "INSERT INTO staged_rows (staged_import_id, sheet_name, row_number, column_map,
+                                      original_values, current_values, issues)
+             VALUES (?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(staged_import_id)
+        .bind(&cells.sheet_name)
+        .bind(cells.row_number)
+        .bind(to_json(&columns)?)
+        .bind(&values_json)
+        .bind(&values_json)
+        .bind(issues.map(|issues| to_json(&issues)).transpose()?)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| format!("Failed to stage row {}: {}", cells.row_number, e))?;
+    }
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit staged import: {}", e))?;
+
+    Ok(json!({
+        "staged_import_id": staged_import_id,
+        "file_name": file_name,
+        "row_count": rows.len(),
+        "invalid_count": rows.len() - valid_cheques.len(),
+        "skipped_sheets": scan.skipped_sheets,
+        "control_totals": check_control_totals(&scan.declared_totals, &valid_cheques)
+    })
+    .to_string())
+}
+
+/// Staged uploads with their row counts, newest first, optionally filtered by status
+#[tauri::command]
+pub async fn get_staged_imports(status: Option<String>, pool: State<'_, SqlitePool>) -> Result<String, String> {
+    let imports: Vec<StagedImport> = sqlx::query_as::<_, StagedImport>(
+        "SELECT si.id, si.file_name, si.status, si.document_id, si.skipped_sheets, si.declared_totals, si.created_by,
+                COUNT(sr.id) AS row_count,
+                COALESCE(SUM(sr.issues IS NOT NULL AND NOT sr.is_deleted), 0) AS invalid_count,
+                COALESCE(SUM(sr.is_deleted), 0) AS deleted_count,
+                si.created_at, si.updated_at
+         FROM staged_imports si
+         LEFT JOIN staged_rows sr ON sr.staged_import_id = si.id
+         WHERE ? IS NULL OR si.status = ?
+         GROUP BY si.id
+         ORDER BY si.created_at DESC, si.id DESC"
+    )
+    .bind(&status)
+    .bind(&status)
+    .fetch_all(pool.inner())
+    .await
+    .map_err(|e| format!("Failed to load staged imports: {}", e))?;
+
+    to_json(&imports)
+}
+
+/// Every row of a staged upload in spreadsheet order, including deleted rows
+#[tauri::command]
+pub async fn get_staged_rows(staged_import_id: i64, pool: State<'_, SqlitePool>) -> Result<String, String> {
+    let rows: Vec<StagedRow> = sqlx::query_as::<_, StagedRow>(
+        "SELECT * FROM staged_rows WHERE staged_import_id = ? ORDER BY id ASC"
+    )
+    .bind(staged_import_id)
+    .fetch_all(pool.inner())
+    .await
+    .map_err(|e| format!("Failed to load staged rows: {}", e))?;
+
+    to_json(&rows)
+}
+
+/// Correct fields of a staged row and revalidate it. `None` or blank text clears
+/// a field. Each changed field is written to the audit trail; the values read
+/// from the file are kept unchanged.
+#[tauri::command]
+pub async fn update_staged_row(
+    staged_row_id: i64,
+    changes: HashMap<ChequeField, Option<String>>,
+    user_id: Option<i64>,
+    pool: State<'_, SqlitePool>,
+) -> Result<String, String> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let row = load_staged_row(&mut tx, staged_row_id).await?;
+    if row.is_deleted {
+        return Err(format!("Row {} of sheet '{}' was deleted and cannot be corrected", row.row_number, row.sheet_name));
+    }
+    let batch = load_staged_batch(&mut tx, row.staged_import_id).await?;
+
+    let mut values: HashMap<ChequeField, StagedValue> = from_json(&row.current_values)?;
+    for field in ChequeField::ALL {
+        let Some(change) = changes.get(&field) else { continue };
+        let new_value = change
+            .as_deref()
+            .map(str::trim)
+            .filter(|text| !text.is_empty())
+            .map(|text| StagedValue::Text(text.to_string()));
+        let old_value = values.get(&field).cloned();
+        if new_value == old_value {
+            continue;
+        }
+
+        record_staged_change(
+            &mut tx,
+            row.id,
+            "staged_row_corrected",
+            old_value.as_ref().map(|value| value.to_cell().to_string()),
+            new_value.as_ref().map(|value| value.to_cell().to_string()),
+            user_id,
+            format!(
+                "Corrected {} on sheet '{}' row {} before import",
+                field.as_str(),
+                row.sheet_name,
+                row.row_number
+            ),
+        )
+        .await?;
+
+        match new_value {
+            Some(value) => values.insert(field, value),
+            None => values.remove(&field),
+        };
+    }
+
+    // The corrected row must respect the payment limits of the user correcting it
+    let mut options = batch.options;
+    if user_id.is_some() {
+        options.user_id = user_id;
+    }
+    let options = with_payment_limits(&mut tx, &options).await?;
+    let issues = validate_cells(&row_cells(&row, &values)?, &batch.mapping, &options).err();
+    sqlx::query("UPDATE staged_rows SET current_values = ?, issues = ? WHERE id = ?")
+        .bind(to_json(&values)?)
+        .bind(issues.map(|issues| to_json(&issues)).transpose()?)
+        .bind(row.id)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| format!("Failed to update staged row: {}", e))?;
+
+    let updated = load_staged_row(&mut tx, row.id).await?;
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit correction: {}", e))?;
+
+    to_json(&updated)
+}
+
+/// Leave a staged row out of the import, recording the deletion in the audit trail
+#[tauri::command]
+pub async fn delete_staged_row(
+    staged_row_id: i64,
+    user_id: Option<i64>,
+    pool: State<'_, SqlitePool>,
+) -> Result<String, String> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let row = load_staged_row(&mut tx, staged_row_id).await?;
+    load_staged_batch(&mut tx, row.staged_import_id).await?;
+    if !row.is_deleted {
+        sqlx::query("UPDATE staged_rows SET is_deleted = 1 WHERE id = ?")
+            .bind(row.id)
+            .execute(&mut tx)
+            .await
+            .map_err(|e| format!("Failed to delete staged row: {}", e))?;
+
+        record_staged_change(
+            &mut tx,
+            row.id,
+            "staged_row_deleted",
+            Some(row.current_values.clone()),
+            None,
+            user_id,
+            format!("Removed sheet '{}' row {} before import", row.sheet_name, row.row_number),
+        )
+        .await?;
+    }
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit deletion: {}", e))?;
+
+    Ok(format!("Row {} of sheet '{}' will not be imported", row.row_number, row.sheet_name))
+}
+
+/// Abandon a staged upload without importing any of its rows
+#[tauri::command]
+pub async fn discard_staged_import(staged_import_id: i64, pool: State<'_, SqlitePool>) -> Result<String, String> {
+    let result = sqlx::query("UPDATE staged_imports SET status = 'discarded' WHERE id = ? AND status = ?")
+        .bind(staged_import_id)
+        .bind(STAGED)
+        .execute(pool.inner())
+        .await
+        .map_err(|e| format!("Failed to discard staged import: {}", e))?;
+
+    if result.rows_affected() == 0 {
+        return Err(format!("Staged import {} is not awaiting review", staged_import_id));
+    }
+    Ok(format!("Staged import {} discarded", staged_import_id))
+}
+
+/// Revalidate the remaining rows of a staged upload and import them exactly as
+/// a direct upload would. Corrections logged against each row are linked to the
+/// cheque it becomes.
+#[tauri::command]
+pub async fn commit_staged_import(
+    staged_import_id: i64,
+    allow_partial: Option<bool>,
+    allow_duplicate_file: Option<bool>,
+    user_id: Option<i64>,
+    pool: State<'_, SqlitePool>,
+) -> Result<String, String> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start import transaction: {}", e))?;
+
+    let batch = load_staged_batch(&mut tx, staged_import_id).await?;
+    let mut options = batch.options;
+    if let Some(allow_partial) = allow_partial {
+        options.allow_partial = allow_partial;
+    }
+    if let Some(allow_duplicate_file) = allow_duplicate_file {
+        options.allow_duplicate_file = allow_duplicate_file;
+    }
+    if user_id.is_some() {
+        options.user_id = user_id;
+    }
+    let options = with_payment_limits(&mut tx, &options).await?;
+
+    let rows: Vec<StagedRow> = sqlx::query_as::<_, StagedRow>(
+        "SELECT * FROM staged_rows WHERE staged_import_id = ? AND NOT is_deleted ORDER BY id ASC"
+    )
+    .bind(staged_import_id)
+    .fetch_all(&mut tx)
+    .await
+    .map_err(|e| format!("Failed to load staged rows: {}", e))?;
+    if rows.is_empty() {
+        return Err(format!("Staged import {} has no rows left to import", staged_import_id));
+    }
+
+    let mut parsed = ParseResult {
+        skipped_sheets: batch.skipped_sheets,
+        declared_totals: batch.declared_totals,
+        ..Default::default()
+    };
+    let mut committed_rows: Vec<i64> = Vec::new();
+    for row in &rows {
+        let values: HashMap<ChequeField, StagedValue> = from_json(&row.current_values)?;
+        match validate_cells(&row_cells(row, &values)?, &batch.mapping, &options) {
+            Ok(cheque) => {
+                parsed.cheques.push(cheque);
+                committed_rows.push(row.id);
+            }
+            Err(issues) => {
+                parsed.rejected_rows += 1;
+                parsed.issues.extend(issues);
+            }
+        }
+    }
+
+    let file_data: Vec<u8> = sqlx::query("SELECT file_data FROM staged_imports WHERE id = ?")
+        .bind(staged_import_id)
+        .fetch_one(&mut tx)
+        .await
+        .map_err(|e| format!("Failed to load staged file: {}", e))?
+        .get("file_data");
+
+    let progress = ImportProgress {
+        rows_total: parsed.cheques.len(),
+        rows_parsed: rows.len(),
+        errors: parsed.issues.len(),
+        ..Default::default()
+    };
+    let saved = save_import(
+        &mut tx,
+        &batch.file_name,
+        &file_data,
+        &parsed,
+        &options,
+        progress,
+        |_| {},
+        &AtomicBool::new(false),
+    )
+    .await?;
+
+    for (staged_row_id, cheque_id) in committed_rows.iter().zip(&saved.cheque_ids) {
+        sqlx::query("UPDATE staged_rows SET cheque_id = ? WHERE id = ?")
+            .bind(cheque_id)
+            .bind(staged_row_id)
+            .execute(&mut tx)
+            .await
+            .map_err(|e| format!("Failed to link staged row to its cheque: {}", e))?;
+
+        sqlx::query("UPDATE audit_trail SET cheque_id = ? WHERE staged_row_id = ?")
+            .bind(cheque_id)
+            .bind(staged_row_id)
+            .execute(&mut tx)
+            .await
+            .map_err(|e| format!("Failed to link corrections to their cheque: {}", e))?;
+    }
+
+    sqlx::query("UPDATE staged_imports SET status = 'committed', document_id = ? WHERE id = ?")
+        .bind(saved.document_id)
+        .bind(staged_import_id)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| format!("Failed to mark staged import as committed: {}", e))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit import: {}", e))?;
+
+    to_json(&saved.response)
+}
+
+/// Settings of a staged upload, failing unless it is still awaiting review
+async fn load_staged_batch(conn: &mut SqliteConnection, staged_import_id: i64) -> Result<StagedBatch, String> {
+    let row = sqlx::query(
+        "SELECT file_name, mapping, options, skipped_sheets, declared_totals, status FROM staged_imports WHERE id = ?"
+    )
+        .bind(staged_import_id)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| format!("Failed to load staged import: {}", e))?
+        .ok_or_else(|| format!("Staged import {} not found", staged_import_id))?;
+
+    let status: String = row.get("status");
+    if status != STAGED {
+        return Err(format!("Staged import {} has already been {}", staged_import_id, status));
+    }
+
+    let skipped_sheets: Option<String> = row.get("skipped_sheets");
+    let declared_totals: Option<String> = row.get("declared_totals");
+    Ok(StagedBatch {
+        file_name: row.get("file_name"),
+        mapping: from_json(row.get("mapping"))?,
+        options: from_json(row.get("options"))?,
+        skipped_sheets: skipped_sheets.map(|json| from_json(&json)).transpose()?.unwrap_or_default(),
+        declared_totals: declared_totals.map(|json| from_json(&json)).transpose()?.unwrap_or_default(),
+    })
+}
+
+async fn load_staged_row(conn: &mut SqliteConnection, staged_row_id: i64) -> Result<StagedRow, String> {
+    sqlx::query_as::<_, StagedRow>("SELECT * FROM staged_rows WHERE id = ?")
+        .bind(staged_row_id)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| format!("Failed to load staged row: {}", e))?
+        .ok_or_else(|| format!("Staged row {} not found", staged_row_id))
+}
+
+/// Cells of a staged row as the parser sees them, using `values` in place of the stored ones
+fn row_cells(row: &StagedRow, values: &HashMap<ChequeField, StagedValue>) -> Result<RowCells, String> {
+    let columns: HashMap<ChequeField, String> = from_json(&row.column_map)?;
+    Ok(RowCells {
+        sheet_name: row.sheet_name.clone(),
+        row_number: row.row_number as u32,
+        columns: columns
+            .into_iter()
+            .filter_map(|(field, letters)| column_letters_to_index(&letters).map(|column| (field, column)))
+            .collect(),
+        values: values.iter().map(|(field, value)| (*field, value.to_cell())).collect(),
+    })
+}
+
+/// Audit a change to a staged row that has no cheque yet; committing links it to one.
+/// Without `user_id` the entry has no user and is attributed to 'Unknown'.
+async fn record_staged_change(
+    conn: &mut SqliteConnection,
+    staged_row_id: i64,
+    action_type: &str,
+    old_value: Option<String>,
+    new_value: Option<String>,
+    user_id: Option<i64>,
+    notes: String,
+) -> Result<(), String> {
+    sqlx::query(
+        "INSERT INTO audit_trail (staged_row_id, action_type, old_value, new_value, user_id, user_name, notes)
+         VALUES (?, ?, ?, ?, ?, COALESCE((SELECT username FROM users WHERE id = ?), 'Unknown'), ?)"
+    )
+    .bind(staged_row_id)
+    .bind(action_type)
+    .bind(old_value)
+    .bind(new_value)
+    .bind(user_id)
+    .bind(user_id)
+    .bind(notes)
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| format!("Failed to write audit trail: {}", e))?;
+    Ok(())
+}
+
+fn to_json<T: Serialize + ?Sized>(value: &T) -> Result<String, String> {
+    serde_json::to_string(value).map_err(|e| format!("Failed to serialize staged data: {}", e))
+}
+
+fn from_json<T: DeserializeOwned>(json: &str) -> Result<T, String> {
+    serde_json::from_str(json).map_err(|e| format!("Staged data is corrupt: {}", e))
+}
@@ -0,0 +1,283 @@
+use std::collections::HashSet;
+use std::fs;
+use chrono::Utc;
+use dirs::download_dir;
+use rust_xlsxwriter::{
+    DataValidation, DataValidationRule, ExcelDateTime, Format, FormatBorder, Workbook, Worksheet, XlsxError,
+};
+use sqlx::SqlitePool;
+use tauri::State;
+use super::amount_parser::DecimalSeparator;
+use super::column_mapping::{
+    column_index_to_letters, column_letters_to_index, load_mapping, ChequeField, ColumnMapping, ColumnRef,
+};
+use super::currency::CURRENCIES;
+use super::payee_delivery::DeliveryMethod;
+
+const DEFAULT_SHEET_NAME: &str = "Cheques";
+const INSTRUCTIONS_SHEET_NAME: &str = "Instructions";
+/// Rows below the header that carry the template's formats and dropdowns
+const TEMPLATE_ROWS: u32 = 5000;
+
+/// Generate a blank import template for a mapping profile and save it to the
+/// Downloads folder, returning its path. Without a profile the template uses
+/// the default auto-detected layout.
+#[tauri::command]
+pub async fn generate_import_template(
+    profile_id: Option<i64>,
+    pool: State<'_, SqlitePool>,
+) -> Result<String, String> {
+    let mapping = load_mapping(pool.inner(), profile_id).await?;
+    let profile_name = match profile_id {
+        Some(id) => sqlx::query_scalar::<_, String>("SELECT name FROM mapping_profiles WHERE id = ?")
+            .bind(id)
+            .fetch_one(pool.inner())
+            .await
+            .map_err(|e| format!("Failed to load mapping profile: {}", e))?,
+        None => "Default".to_string(),
+    };
+
+    let data = build_import_template(&mapping, &profile_name)?;
+
+    let mut file_path = download_dir().ok_or("Could not locate Downloads folder")?;
+    let safe_name: String = profile_name
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == ' ' { c } else { '_' })
+        .collect();
+    file_path.push(format!("{}_{} import template.xlsx", Utc::now().timestamp(), safe_name.trim()));
+
+    fs::write(&file_path, &data).map_err(|e| format!("Failed to write template to Downloads: {}", e))?;
+    Ok(file_path.to_string_lossy().to_string())
+}
+
+/// Column of the template and the field it holds
+#[derive(Debug, Clone)]
+pub struct TemplateColumn {
+    pub field: ChequeField,
+    /// 0-based column index
+    pub index: u32,
+    pub header: String,
+}
+
+/// Lay out the template's columns so the mapping resolves every field: fields
+/// pinned to a column letter keep it, fields named by header use that header,
+/// and the rest follow in the free columns under a recognised header.
+pub fn template_columns(mapping: &ColumnMapping) -> Result<Vec<TemplateColumn>, String> {
+    let mut columns: Vec<TemplateColumn> = Vec::new();
+    let mut taken: HashSet<u32> = HashSet::new();
+
+    for field in ChequeField::ALL {
+        if let Some(ColumnRef::Column(letters)) = mapping.columns.get(&field) {
+            let index = column_letters_to_index(letters)
+                .ok_or_else(|| format!("Invalid column letter '{}' for {}", letters, field.as_str()))?;
+            if !taken.insert(index) {
+                return Err(format!("Column {} is mapped to more than one field", letters.trim().to_uppercase()));
+            }
+            columns.push(TemplateColumn { field, index, header: header_label(field).to_string() });
+        }
+    }
+
+    let mut next_free = 0;
+    for field in ChequeField::ALL {
+        let header = match mapping.columns.get(&field) {
+            Some(ColumnRef::Column(_)) => continue,
+            Some(ColumnRef::Header(name)) => name.clone(),
+            None => header_label(field).to_string(),
+        };
+        while taken.contains(&next_free) {
+            next_free += 1;
+        }
+        taken.insert(next_free);
+        columns.push(TemplateColumn { field, index: next_free, header });
+    }
+
+    columns.sort_by_key(|column| column.index);
+    Ok(columns)
+}
+
+/// Build the template workbook: a data sheet with headers, number formats and
+/// dropdowns matching the mapping, followed by an instructions sheet
+pub fn build_import_template(mapping: &ColumnMapping, profile_name: &str) -> Result<Vec<u8>, String> {
+    let columns = template_columns(mapping)?;
+    let mut workbook = Workbook::new();
+    write_data_sheet(workbook.add_worksheet(), mapping, &columns).map_err(template_error)?;
+    write_instructions_sheet(workbook.add_worksheet(), mapping, &columns, profile_name).map_err(template_error)?;
+    workbook.save_to_buffer().map_err(template_error)
+}
+
+fn write_data_sheet(
+    sheet: &mut Worksheet,
+    mapping: &ColumnMapping,
+    columns: &[TemplateColumn],
+) -> Result<(), XlsxError> {
+    sheet.set_name(mapping.sheet_name.as_deref().unwrap_or(DEFAULT_SHEET_NAME))?;
+
+    let header_row = mapping.header_row - 1;
+    let first_row = header_row + 1;
+    let last_row = header_row + TEMPLATE_ROWS;
+    let header_format = Format::new().set_bold().set_border_bottom(FormatBorder::Thin);
+    let required_format = header_format.clone().set_background_color("#FFF2CC");
+
+    for column in columns {
+        let col = column.index as u16;
+        let format = if column.field.is_required() { &required_format } else { &header_format };
+        sheet.write_string_with_format(header_row, col, &column.header, format)?;
+        sheet.set_column_width(col, (column.header.len() as f64 + 4.0).max(14.0))?;
+
+        if let Some(number_format) = number_format(column.field) {
+            sheet.set_column_format(col, &Format::new().set_num_format(number_format))?;
+        }
+        if let Some(validation) = validation(column.field)? {
+            sheet.add_data_validation(first_row, col, last_row, col, &validation)?;
+        }
+    }
+
+    sheet.set_freeze_panes(first_row, 0)?;
+    Ok(())
+}
+
+fn write_instructions_sheet(
+    sheet: &mut Worksheet,
+    mapping: &ColumnMapping,
+    columns: &[TemplateColumn],
+    profile_name: &str,
+) -> Result<(), XlsxError> {
+    sheet.set_name(INSTRUCTIONS_SHEET_NAME)?;
+    let bold = Format::new().set_bold();
+    let wrap = Format::new().set_text_wrap();
+    let data_sheet = mapping.sheet_name.as_deref().unwrap_or(DEFAULT_SHEET_NAME);
+
+    let mut notes = vec![
+        format!("Import template for mapping profile '{}'.", profile_name),
+        format!(
+            "Enter one cheque per row on the '{}' sheet, starting on row {}. Do not rename, move or remove the headers in row {}.",
+            data_sheet,
+            mapping.header_row + 1,
+            mapping.header_row
+        ),
+        "Highlighted columns are required. Leave optional cells blank when they do not apply.".to_string(),
+        format!(
+            "Amounts typed as text use {} as the decimal separator.",
+            match mapping.decimal_separator {
+                DecimalSeparator::Point => "a point (1,234.50)",
+                DecimalSeparator::Comma => "a comma (1.234,50)",
+                DecimalSeparator::Auto => "either a point or a comma",
+            }
+        ),
+    ];
+    if let Some(digits) = mapping.cheque_number_digits {
+        notes.push(format!("Cheque numbers are padded with leading zeros to {} digits.", digits));
+    }
+
+    let mut row = 0;
+    sheet.write_string_with_format(row, 0, "How to fill in this template", &bold)?;
+    row += 1;
+    for note in &notes {
+        sheet.write_string(row, 0, note)?;
+        row += 1;
+    }
+
+    row += 1;
+    for (col, heading) in ["Column", "Header", "Required", "What to enter"].iter().enumerate() {
+        sheet.write_string_with_format(row, col as u16, *heading, &bold)?;
+    }
+    for column in columns {
+        row += 1;
+        sheet.write_string(row, 0, column_index_to_letters(column.index))?;
+        sheet.write_string(row, 1, &column.header)?;
+        sheet.write_string(row, 2, if column.field.is_required() { "Yes" } else { "No" })?;
+        sheet.write_string_with_format(row, 3, field_instructions(column.field), &wrap)?;
+    }
+
+    sheet.set_column_width(0, 12)?;
+    sheet.set_column_width(1, 22)?;
+    sheet.set_column_width(2, 10)?;
+    sheet.set_column_width(3, 80)?;
+    Ok(())
+}
+
+/// Header written for fields the profile does not name; each is one of the
+/// spellings auto-detection recognises
+fn header_label(field: ChequeField) -> &'static str {
+    match field {
+        ChequeField::ChequeNumber => "Cheque Number",
+        ChequeField::Amount => "Amount",
+        ChequeField::ClientName => "Payee",
+        ChequeField::AccountNumber => "Account Number",
+        ChequeField::IssueDate => "Issue Date",
+        ChequeField::Memo => "Memo",
+        ChequeField::Remarks => "Remarks",
+        ChequeField::AddressLine1 => "Address Line 1",
+        ChequeField::AddressLine2 => "Address Line 2",
+        ChequeField::City => "City",
+        ChequeField::Region => "Region",
+        ChequeField::PostalCode => "Postal Code",
+        ChequeField::Country => "Country",
+        ChequeField::DeliveryMethod => "Delivery Method",
+        ChequeField::Currency => "Currency",
+    }
+}
+
+fn field_instructions(field: ChequeField) -> &'static str {
+    match field {
+        ChequeField::ChequeNumber => "Cheque number exactly as it should print. Stored as text, so leading zeros are kept.",
+        ChequeField::Amount => "Cheque amount, greater than zero, with no more decimal places than its currency uses.",
+        ChequeField::ClientName => "Name of the payee as it should print on the cheque.",
+        ChequeField::AccountNumber => "Paying account. Leave blank to use the account chosen at upload.",
+        ChequeField::IssueDate => "Date printed on the cheque, entered as a date (YYYY-MM-DD).",
+        ChequeField::Memo => "Memo line printed on the cheque.",
+        ChequeField::Remarks => "Internal note or reference; not printed.",
+        ChequeField::AddressLine1 => "First line of the payee's mailing address. Required for mail and courier delivery.",
+        ChequeField::AddressLine2 => "Second address line, such as a suite or unit.",
+        ChequeField::City => "City of the mailing address. Required for mail and courier delivery.",
+        ChequeField::Region => "State, province or county.",
+        ChequeField::PostalCode => "Postal or ZIP code. Required for mail and courier delivery.",
+        ChequeField::Country => "Country of the mailing address.",
+        ChequeField::DeliveryMethod => "How the cheque reaches the payee: choose mail, pickup or courier.",
+        ChequeField::Currency => "Currency code of the amount, such as USD. Leave blank to use the currency chosen at upload.",
+    }
+}
+
+fn number_format(field: ChequeField) -> Option<&'static str> {
+    match field {
+        ChequeField::Amount => Some("#,##0.00"),
+        ChequeField::IssueDate => Some("yyyy-mm-dd"),
+        // Text, so Excel does not drop leading zeros or reformat long numbers
+        ChequeField::ChequeNumber | ChequeField::AccountNumber | ChequeField::PostalCode => Some("@"),
+        _ => None,
+    }
+}
+
+fn validation(field: ChequeField) -> Result<Option<DataValidation>, XlsxError> {
+    let validation = match field {
+        ChequeField::DeliveryMethod => {
+            let methods = [DeliveryMethod::Mail, DeliveryMethod::Pickup, DeliveryMethod::Courier].map(|m| m.as_str());
+            DataValidation::new()
+                .allow_list_strings(&methods)?
+                .set_error_title("Delivery method")?
+                .set_error_message("Choose mail, pickup or courier.")?
+        }
+        ChequeField::Currency => {
+            let codes: Vec<&str> = CURRENCIES.iter().map(|currency| currency.code).collect();
+            DataValidation::new()
+                .allow_list_strings(&codes)?
+                .set_error_title("Currency")?
+                .set_error_message(format!("Choose one of {}.", codes.join(", ")))?
+        }
+        ChequeField::Amount => DataValidation::new()
+            .allow_decimal_number(DataValidationRule::GreaterThan(0.0))
+            .set_error_title("Amount")?
+            .set_error_message("Enter an amount greater than zero.")?,
+        ChequeField::IssueDate => DataValidation::new()
+            .allow_date(DataValidationRule::GreaterThan(ExcelDateTime::from_ymd(1900, 1, 1)?))
+            .set_error_title("Issue date")?
+            .set_error_message("Enter the cheque date as a date, for example 2024-03-31.")?,
+        _ => return Ok(None),
+    };
+    Ok(Some(validation))
+}
+
+fn template_error(e: XlsxError) -> String {
+    format!("Failed to build import template: {}", e)
+}
+
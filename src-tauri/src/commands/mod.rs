@@ -1,9 +1,39 @@
+pub mod amount_line;
+pub mod analytics_query;
+pub mod amount_parser;
+pub mod app_settings;
+pub mod column_mapping;
+pub mod control_totals;
+pub mod currency;
+pub mod delimited_text;
+pub mod document_hash;
+pub mod duplicate_payments;
+pub mod encrypted_workbook;
 pub mod excel_parser;
 pub mod file_uploader;
 pub mod file_manager;
+pub mod file_signatures;
+pub mod hot_folder;
+pub mod import_issues;
+pub mod import_jobs;
+pub mod import_parser;
+pub mod import_staging;
+pub mod import_template;
+pub mod number_words;
 pub mod ocr_processor;
+pub mod payee_delivery;
+pub mod payment_limits;
+pub mod permissions;
+pub mod spreadsheet;
 
-pub use excel_parser::parse_excel_to_cheques;
+pub use currency::get_currencies;
+pub use excel_parser::{list_spreadsheet_sheets, parse_excel_to_cheques};
 pub use file_uploader::upload_excel_as_blob;
+pub use import_jobs::{cancel_import_job, start_import_job, ImportJobs};
+pub use import_staging::{
+    commit_staged_import, delete_staged_row, discard_staged_import, get_staged_imports, get_staged_rows,
+    stage_import, update_staged_row,
+};
+pub use import_template::generate_import_template;
 pub use file_manager::open_excel_from_database;
-pub use ocr_processor::convert_amount_to_words;
+pub use ocr_processor::{cheque_amount_in_words, convert_amount_to_words};
@@ -0,0 +1,561 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use sqlx::SqliteConnection;
+use super::amount_line::AmountLineFormat;
+use super::app_settings::get_setting;
+
+pub const AMOUNT_WORDS_KEY: &str = "amount_words";
+
+/// Language amounts are written in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Language {
+    #[default]
+    #[serde(rename = "en")]
+    English,
+    #[serde(rename = "fr")]
+    French,
+    #[serde(rename = "es")]
+    Spanish,
+}
+
+impl Language {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::French => "fr",
+            Language::Spanish => "es",
+        }
+    }
+
+    /// Speller for the language. Only English amounts are written differently
+    /// under each numbering system; French and Spanish have a single usage.
+    pub fn speller(&self, numbering: NumberingSystem) -> &'static dyn NumberSpeller {
+        match (self, numbering) {
+            (Language::English, NumberingSystem::International) => &ENGLISH_INTERNATIONAL,
+            (Language::English, NumberingSystem::British) => &ENGLISH_BRITISH,
+            (Language::English, NumberingSystem::American) => &ENGLISH_AMERICAN,
+            (Language::English, NumberingSystem::Indian) => &ENGLISH_INDIAN,
+            (Language::French, _) => &French,
+            (Language::Spanish, _) => &Spanish,
+        }
+    }
+}
+
+/// How English amounts are grouped and where "and" goes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NumberingSystem {
+    /// Thousands and millions, with "and" only inside hundreds: "One Thousand Five"
+    #[default]
+    International,
+    /// As international, plus "and" before a final part below a hundred:
+    /// "One Thousand and Five"
+    British,
+    /// No "and" anywhere: "One Hundred Twenty-Five"
+    American,
+    /// Thousands, lakhs (100,000) and crores (10,000,000): "Twelve Lakh Fifty Thousand"
+    Indian,
+}
+
+impl NumberingSystem {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NumberingSystem::International => "international",
+            NumberingSystem::British => "british",
+            NumberingSystem::American => "american",
+            NumberingSystem::Indian => "indian",
+        }
+    }
+}
+
+impl std::str::FromStr for NumberingSystem {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "international" => Ok(NumberingSystem::International),
+            "british" => Ok(NumberingSystem::British),
+            "american" => Ok(NumberingSystem::American),
+            "indian" => Ok(NumberingSystem::Indian),
+            other => Err(format!(
+                "Unknown numbering system '{}'; use international, british, american or indian",
+                other
+            )),
+        }
+    }
+}
+
+/// How amounts in words are written: the numbering system, overridable per
+/// paying account, and the amount line format of the cheque stock
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AmountWordsSettings {
+    pub numbering_system: NumberingSystem,
+    /// Numbering system by account number, for accounts that differ from the default
+    pub account_numbering: HashMap<String, NumberingSystem>,
+    pub amount_line: AmountLineFormat,
+}
+
+impl AmountWordsSettings {
+    /// Numbering system for a cheque: its own choice, then its account's, then the default
+    pub fn numbering_for(&self, cheque_numbering: Option<NumberingSystem>, account_number: Option<&str>) -> NumberingSystem {
+        cheque_numbering
+            .or_else(|| account_number.and_then(|account| self.account_numbering.get(account.trim()).copied()))
+            .unwrap_or(self.numbering_system)
+    }
+}
+
+pub async fn load_amount_words_settings(conn: &mut SqliteConnection) -> Result<AmountWordsSettings, String> {
+    Ok(get_setting(conn, AMOUNT_WORDS_KEY).await?.unwrap_or_default())
+}
+
+/// Grammatical gender of the noun a number counts, which French and Spanish
+/// numbers agree with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Gender {
+    #[default]
+    Masculine,
+    Feminine,
+}
+
+/// Singular and plural name of a currency unit in one language
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct UnitName {
+    pub singular: &'static str,
+    pub plural: &'static str,
+    pub gender: Gender,
+}
+
+/// Spells whole numbers, and counts of a currency unit, in one language
+pub trait NumberSpeller: Sync {
+    /// `n` written as a cardinal number in front of a noun of `gender`
+    fn spell(&self, n: u64, gender: Gender) -> String;
+
+    /// Whether a count of `n` takes the singular form of a noun
+    fn is_singular(&self, n: u64) -> bool {
+        n == 1
+    }
+
+    /// Word joining the major and minor unit parts of an amount
+    fn and_word(&self) -> &'static str;
+
+    /// Word closing an amount so nothing can be written after it, such as "Only"
+    fn only_word(&self) -> &'static str;
+
+    /// `n` followed by the unit's name, such as "twenty dollars"
+    fn count_units(&self, n: u64, unit: &UnitName) -> String {
+        let name = if self.is_singular(n) { unit.singular } else { unit.plural };
+        format!("{} {}", self.spell(n, unit.gender), name)
+    }
+}
+
+pub struct English {
+    numbering: NumberingSystem,
+}
+
+static ENGLISH_INTERNATIONAL: English = English { numbering: NumberingSystem::International };
+static ENGLISH_BRITISH: English = English { numbering: NumberingSystem::British };
+static ENGLISH_AMERICAN: English = English { numbering: NumberingSystem::American };
+static ENGLISH_INDIAN: English = English { numbering: NumberingSystem::Indian };
+
+static ONES: [&str; 10] = [
+    "Zero", "One", "Two", "Three", "Four", "Five", "Six", "Seven", "Eight", "Nine"
+];
+static TEENS: [&str; 10] = [
+    "Ten", "Eleven", "Twelve", "Thirteen", "Fourteen", "Fifteen", "Sixteen",
+    "Seventeen", "Eighteen", "Nineteen"
+];
+static TENS: [&str; 10] = [
+    "", "", "Twenty", "Thirty", "Forty", "Fifty", "Sixty", "Seventy", "Eighty", "Ninety"
+];
+// Short scale names up to the largest u64
+static THOUSANDS: [&str; 7] = [
+    "", "Thousand", "Million", "Billion", "Trillion", "Quadrillion", "Quintillion",
+];
+
+impl English {
+    fn chunk_to_words(&self, n: u64) -> String {
+        match n {
+            0..=9 => ONES[n as usize].to_string(),
+            10..=19 => TEENS[n as usize - 10].to_string(),
+            20..=99 => {
+                if n.is_multiple_of(10) {
+                    TENS[n as usize / 10].to_string()
+                } else {
+                    format!("{}-{}", TENS[n as usize / 10], ONES[n as usize % 10])
+                }
+            }
+            100..=999 => {
+                if n.is_multiple_of(100) {
+                    format!("{} Hundred", ONES[n as usize / 100])
+                } else if self.numbering == NumberingSystem::American {
+                    format!("{} Hundred {}", ONES[n as usize / 100], self.chunk_to_words(n % 100))
+                } else {
+                    format!("{} Hundred and {}", ONES[n as usize / 100], self.chunk_to_words(n % 100))
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl NumberSpeller for English {
+    fn spell(&self, n: u64, _gender: Gender) -> String {
+        if n == 0 {
+            return "Zero".to_string();
+        }
+        let mut words: Vec<String> = vec![];
+
+        if self.numbering == NumberingSystem::Indian {
+            let crores = n / 10_000_000;
+            if crores > 0 {
+                words.push(format!("{} Crore", self.spell(crores, Gender::Masculine)));
+            }
+            for (count, name) in [(n / 100_000 % 100, "Lakh"), (n / 1000 % 100, "Thousand")] {
+                if count > 0 {
+                    words.push(format!("{} {}", self.chunk_to_words(count), name));
+                }
+            }
+        } else {
+            let mut number: u64 = n / 1000;
+            let mut index: usize = 1;
+            while number > 0 {
+                if !number.is_multiple_of(1000) {
+                    words.insert(0, format!(
+                        "{} {}",
+                        self.chunk_to_words(number % 1000),
+                        THOUSANDS[index]
+                    ));
+                }
+                number /= 1000;
+                index += 1;
+            }
+        }
+
+        let rest = n % 1000;
+        if rest > 0 {
+            if self.numbering == NumberingSystem::British && rest < 100 && !words.is_empty() {
+                words.push("and".to_string());
+            }
+            words.push(self.chunk_to_words(rest));
+        }
+        words.join(" ")
+    }
+
+    fn and_word(&self) -> &'static str {
+        "and"
+    }
+
+    fn only_word(&self) -> &'static str {
+        "Only"
+    }
+}
+
+/// French, in the traditional spelling used on cheques: "vingt et un",
+/// "quatre-vingts", "deux cents" but "deux cent mille"
+pub struct French;
+
+static FRENCH_UNITS: [&str; 20] = [
+    "zéro", "un", "deux", "trois", "quatre", "cinq", "six", "sept", "huit", "neuf",
+    "dix", "onze", "douze", "treize", "quatorze", "quinze", "seize", "dix-sept", "dix-huit", "dix-neuf",
+];
+// Long scale names, largest first, down to the largest u64
+static FRENCH_SCALES: [(u64, &str); 5] = [
+    (1_000_000_000_000_000_000, "trillion"),
+    (1_000_000_000_000_000, "billiard"),
+    (1_000_000_000_000, "billion"),
+    (1_000_000_000, "milliard"),
+    (1_000_000, "million"),
+];
+static FRENCH_TENS: [&str; 10] = [
+    "", "", "vingt", "trente", "quarante", "cinquante", "soixante", "soixante", "quatre-vingt", "quatre-vingt",
+];
+
+impl French {
+    fn unit(n: u64, gender: Gender) -> &'static str {
+        if n == 1 && gender == Gender::Feminine { "une" } else { FRENCH_UNITS[n as usize] }
+    }
+
+    /// 0..=99. `plural_ending` is false before "mille", which stops "quatre-vingts"
+    /// and "cents" taking their plural s.
+    fn below_hundred(n: u64, gender: Gender, plural_ending: bool) -> String {
+        if n < 20 {
+            return Self::unit(n, gender).to_string();
+        }
+        let tens = n / 10;
+        // Seventy and ninety count on from sixty and eighty: "soixante-dix", "quatre-vingt-dix"
+        let rest = if tens == 7 || tens == 9 { n % 10 + 10 } else { n % 10 };
+        let base = FRENCH_TENS[tens as usize];
+        match rest {
+            0 if tens == 8 && plural_ending => "quatre-vingts".to_string(),
+            0 => base.to_string(),
+            1 | 11 if tens < 8 => format!("{} et {}", base, Self::unit(rest, gender)),
+            _ => format!("{}-{}", base, Self::unit(rest, gender)),
+        }
+    }
+
+    fn below_thousand(n: u64, gender: Gender, plural_ending: bool) -> String {
+        let (hundreds, rest) = (n / 100, n % 100);
+        let tail = Self::below_hundred(rest, gender, plural_ending);
+        match (hundreds, rest) {
+            (0, _) => tail,
+            (1, 0) => "cent".to_string(),
+            (1, _) => format!("cent {}", tail),
+            (_, 0) if plural_ending => format!("{} cents", FRENCH_UNITS[hundreds as usize]),
+            (_, 0) => format!("{} cent", FRENCH_UNITS[hundreds as usize]),
+            _ => format!("{} cent {}", FRENCH_UNITS[hundreds as usize], tail),
+        }
+    }
+}
+
+impl NumberSpeller for French {
+    fn spell(&self, n: u64, gender: Gender) -> String {
+        if n == 0 {
+            return "zéro".to_string();
+        }
+        let mut words: Vec<String> = Vec::new();
+        // The scale names are nouns: they agree in number, are always preceded
+        // by their count ("un million") and leave "cents" and "quatre-vingts"
+        // in front of them plural
+        for (scale, name) in FRENCH_SCALES {
+            let count = n / scale % 1000;
+            if count > 0 {
+                let plural = if count == 1 { "" } else { "s" };
+                words.push(format!("{} {}{}", Self::below_thousand(count, Gender::Masculine, true), name, plural));
+            }
+        }
+        let thousands = n / 1000 % 1000;
+        match thousands {
+            0 => {}
+            // Mille is invariable and never preceded by "un"
+            1 => words.push("mille".to_string()),
+            _ => words.push(format!("{} mille", Self::below_thousand(thousands, Gender::Masculine, false))),
+        }
+        let rest = n % 1000;
+        if rest > 0 {
+            words.push(Self::below_thousand(rest, gender, true));
+        }
+        words.join(" ")
+    }
+
+    fn is_singular(&self, n: u64) -> bool {
+        n < 2
+    }
+
+    fn and_word(&self) -> &'static str {
+        "et"
+    }
+
+    fn only_word(&self) -> &'static str {
+        "seulement"
+    }
+
+    /// Whole millions, milliards and larger take "de": "un million de dollars"
+    fn count_units(&self, n: u64, unit: &UnitName) -> String {
+        let name = if self.is_singular(n) { unit.singular } else { unit.plural };
+        let linker = if n >= 1_000_000 && n.is_multiple_of(1_000_000) { " de" } else { "" };
+        format!("{}{} {}", self.spell(n, unit.gender), linker, name)
+    }
+}
+
+/// Spanish, with "un"/"una" agreeing with the counted noun and the long
+/// scale: "mil millones" for a thousand million, "billón" for a million million
+/// and "trillón" for a million billón
+pub struct Spanish;
+
+static SPANISH_UNITS: [&str; 30] = [
+    "cero", "uno", "dos", "tres", "cuatro", "cinco", "seis", "siete", "ocho", "nueve",
+    "diez", "once", "doce", "trece", "catorce", "quince", "dieciséis", "diecisiete", "dieciocho", "diecinueve",
+    "veinte", "veintiuno", "veintidós", "veintitrés", "veinticuatro", "veinticinco", "veintiséis", "veintisiete",
+    "veintiocho", "veintinueve",
+];
+// Long scale names, largest first, down to the largest u64
+static SPANISH_SCALES: [(u64, &str, &str); 3] = [
+    (1_000_000_000_000_000_000, "trillón", "trillones"),
+    (1_000_000_000_000, "billón", "billones"),
+    (1_000_000, "millón", "millones"),
+];
+static SPANISH_TENS: [&str; 10] = [
+    "", "", "", "treinta", "cuarenta", "cincuenta", "sesenta", "setenta", "ochenta", "noventa",
+];
+static SPANISH_HUNDREDS: [&str; 10] = [
+    "", "ciento", "doscientos", "trescientos", "cuatrocientos", "quinientos", "seiscientos", "setecientos",
+    "ochocientos", "novecientos",
+];
+
+impl Spanish {
+    /// 0..=99 in front of a noun, where "uno" shortens to "un" or becomes "una"
+    fn below_hundred(n: u64, gender: Gender) -> String {
+        let one = |masculine: &'static str, feminine: &'static str| match gender {
+            Gender::Masculine => masculine,
+            Gender::Feminine => feminine,
+        };
+        match n {
+            1 => one("un", "una").to_string(),
+            21 => one("veintiún", "veintiuna").to_string(),
+            0..=29 => SPANISH_UNITS[n as usize].to_string(),
+            _ if n.is_multiple_of(10) => SPANISH_TENS[n as usize / 10].to_string(),
+            _ if n % 10 == 1 => format!("{} y {}", SPANISH_TENS[n as usize / 10], one("un", "una")),
+            _ => format!("{} y {}", SPANISH_TENS[n as usize / 10], SPANISH_UNITS[n as usize % 10]),
+        }
+    }
+
+    fn below_thousand(n: u64, gender: Gender) -> String {
+        let (hundreds, rest) = (n / 100, n % 100);
+        if n == 100 {
+            return "cien".to_string();
+        }
+        // Hundreds from two hundred up agree with the noun: "doscientas libras"
+        let hundreds_word = match gender {
+            Gender::Feminine if hundreds > 1 => SPANISH_HUNDREDS[hundreds as usize].replace("ientos", "ientas"),
+            _ => SPANISH_HUNDREDS[hundreds as usize].to_string(),
+        };
+        match (hundreds, rest) {
+            (0, _) => Self::below_hundred(rest, gender),
+            (_, 0) => hundreds_word,
+            _ => format!("{} {}", hundreds_word, Self::below_hundred(rest, gender)),
+        }
+    }
+
+    /// 0..=999_999, with "mil" never preceded by "un"
+    fn below_million(n: u64, gender: Gender) -> String {
+        let (thousands, rest) = (n / 1000, n % 1000);
+        let mut words: Vec<String> = Vec::new();
+        match thousands {
+            0 => {}
+            1 => words.push("mil".to_string()),
+            _ => words.push(format!("{} mil", Self::below_thousand(thousands, gender))),
+        }
+        if rest > 0 || thousands == 0 {
+            words.push(Self::below_thousand(rest, gender));
+        }
+        words.join(" ")
+    }
+}
+
+impl NumberSpeller for Spanish {
+    fn spell(&self, n: u64, gender: Gender) -> String {
+        if n == 0 {
+            return "cero".to_string();
+        }
+        let mut words: Vec<String> = Vec::new();
+        // Millón, billón and trillón are masculine nouns whatever is being counted
+        for (scale, singular, plural) in SPANISH_SCALES {
+            let count = n / scale % 1_000_000;
+            if count > 0 {
+                let name = if count == 1 { singular } else { plural };
+                words.push(format!("{} {}", Self::below_million(count, Gender::Masculine), name));
+            }
+        }
+        let rest = n % 1_000_000;
+        if rest > 0 {
+            words.push(Self::below_million(rest, gender));
+        }
+        words.join(" ")
+    }
+
+    fn and_word(&self) -> &'static str {
+        "con"
+    }
+
+    fn only_word(&self) -> &'static str {
+        "solamente"
+    }
+
+    /// Whole millions take "de": "un millón de dólares"
+    fn count_units(&self, n: u64, unit: &UnitName) -> String {
+        let name = if self.is_singular(n) { unit.singular } else { unit.plural };
+        let linker = if n >= 1_000_000 && n.is_multiple_of(1_000_000) { " de" } else { "" };
+        format!("{}{} {}", self.spell(n, unit.gender), linker, name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn english(numbering: NumberingSystem, n: u64) -> String {
+        Language::English.speller(numbering).spell(n, Gender::Masculine)
+    }
+
+    #[test]
+    fn spells_english_in_each_numbering_system() {
+        assert_eq!(english(NumberingSystem::International, 0), "Zero");
+        assert_eq!(english(NumberingSystem::International, 1005), "One Thousand Five");
+        assert_eq!(english(NumberingSystem::British, 1005), "One Thousand and Five");
+        assert_eq!(english(NumberingSystem::American, 125), "One Hundred Twenty-Five");
+        assert_eq!(english(NumberingSystem::International, 125), "One Hundred and Twenty-Five");
+        assert_eq!(english(NumberingSystem::Indian, 1_250_000), "Twelve Lakh Fifty Thousand");
+        assert_eq!(english(NumberingSystem::Indian, 1_000_000_000), "One Hundred Crore");
+        assert_eq!(english(NumberingSystem::International, 2_000_001_000), "Two Billion One Thousand");
+        assert_eq!(
+            english(NumberingSystem::International, u64::MAX),
+            "Eighteen Quintillion Four Hundred and Forty-Six Quadrillion Seven Hundred and Forty-Four Trillion \
+             Seventy-Three Billion Seven Hundred and Nine Million Five Hundred and Fifty-One Thousand \
+             Six Hundred and Fifteen"
+        );
+    }
+
+    #[test]
+    fn spells_french() {
+        let spell = |n| French.spell(n, Gender::Masculine);
+        assert_eq!(spell(21), "vingt et un");
+        assert_eq!(spell(71), "soixante et onze");
+        assert_eq!(spell(80), "quatre-vingts");
+        assert_eq!(spell(91), "quatre-vingt-onze");
+        assert_eq!(spell(200), "deux cents");
+        assert_eq!(spell(200_000), "deux cent mille");
+        assert_eq!(spell(80_000), "quatre-vingt mille");
+        assert_eq!(spell(1000), "mille");
+        assert_eq!(spell(200_000_000), "deux cents millions");
+        assert_eq!(spell(1_000_000_000), "un milliard");
+        assert_eq!(French.spell(21, Gender::Feminine), "vingt et une");
+    }
+
+    #[test]
+    fn spells_french_beyond_milliards() {
+        let spell = |n| French.spell(n, Gender::Masculine);
+        assert_eq!(spell(1_000_000_000_000), "un billion");
+        assert_eq!(spell(3_000_000_000_000_000), "trois billiards");
+        assert_eq!(spell(1_002_000_000_000_000), "un billiard deux billions");
+        assert_eq!(spell(5_000_000_000_000_000_000), "cinq trillions");
+        assert_eq!(
+            spell(u64::MAX),
+            "dix-huit trillions quatre cent quarante-six billiards sept cent quarante-quatre billions \
+             soixante-treize milliards sept cent neuf millions cinq cent cinquante et un mille six cent quinze"
+        );
+        let dollar = UnitName { singular: "dollar", plural: "dollars", gender: Gender::Masculine };
+        assert_eq!(French.count_units(2_000_000_000_000_000, &dollar), "deux billiards de dollars");
+    }
+
+    #[test]
+    fn spells_spanish() {
+        let spell = |n| Spanish.spell(n, Gender::Masculine);
+        assert_eq!(spell(1), "un");
+        assert_eq!(spell(21), "veintiún");
+        assert_eq!(spell(100), "cien");
+        assert_eq!(spell(101), "ciento un");
+        assert_eq!(spell(1000), "mil");
+        assert_eq!(spell(21_000_000), "veintiún millones");
+        assert_eq!(spell(1_000_000_000), "mil millones");
+        assert_eq!(spell(1_000_000_000_000), "un billón");
+        assert_eq!(Spanish.spell(200, Gender::Feminine), "doscientas");
+        assert_eq!(Spanish.spell(31, Gender::Feminine), "treinta y una");
+    }
+
+    #[test]
+    fn spells_spanish_beyond_billones() {
+        let spell = |n| Spanish.spell(n, Gender::Masculine);
+        assert_eq!(spell(1_000_000_000_000_000_000), "un trillón");
+        assert_eq!(spell(2_000_000_000_000_000_000), "dos trillones");
+        assert_eq!(spell(1_000_001_000_000_000_000), "un trillón un billón");
+        assert_eq!(
+            spell(u64::MAX),
+            "dieciocho trillones cuatrocientos cuarenta y seis mil setecientos cuarenta y cuatro billones \
+             setenta y tres mil setecientos nueve millones quinientos cincuenta y un mil seiscientos quince"
+        );
+        let dollar = UnitName { singular: "dólar", plural: "dólares", gender: Gender::Masculine };
+        assert_eq!(Spanish.count_units(2_000_000_000_000_000_000, &dollar), "dos trillones de dólares");
+    }
+}
@@ -1,94 +1,135 @@
-const MAX_POSSIBLE_CHEQUE_PAYMENT: f64 = 25_000_000.00;
-
-static ONES: [&str; 10] = [
-    "Zero", "One", "Two", "Three", "Four", "Five", "Six", "Seven", "Eight", "Nine"
-];
-static TEENS: [&str; 10] = [
-    "Ten", "Eleven", "Twelve", "Thirteen", "Fourteen", "Fifteen", "Sixteen", 
-    "Seventeen", "Eighteen", "Nineteen"
-];
-static TENS: [&str; 10] = [
-    "", "", "Twenty", "Thirty", "Forty", "Fifty", "Sixty", "Seventy", "Eighty", "Ninety"
-];
-static THOUSANDS: [&str; 3] = [
-    "", "Thousand", "Million",
-];
+use rust_decimal::Decimal;
+use sqlx::SqlitePool;
+use tauri::State;
+use super::amount_line::{AmountLine, AmountLineFormat, MinorUnitStyle};
+use super::currency::{default_currency, find_currency, parse_currency, Currency, DEFAULT_CURRENCY};
+use super::number_words::{load_amount_words_settings, Language, NumberingSystem};
+use super::payment_limits::load_payment_limits;
 
+/// Write `amount` in words as it goes on the cheque face, using the unit names
+/// of `currency`, which defaults to US dollars, and returning an `AmountLine`
+/// as JSON. With `second_language` the amount is written in both languages, as
+/// bilingual cheques print it. English amounts follow `numbering_system`,
+/// international by default. Amounts over the maximum cheque amount in the
+/// currency are refused.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn convert_amount_to_words(
+    amount: Decimal,
+    name: &str,
+    currency: Option<&str>,
+    language: Option<Language>,
+    second_language: Option<Language>,
+    numbering_system: Option<NumberingSystem>,
+    format: Option<AmountLineFormat>,
+    pool: State<'_, SqlitePool>,
+) -> Result<String, String> {
+    let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+    let limit_currency = parse_currency(currency.unwrap_or(DEFAULT_CURRENCY))?;
+    load_payment_limits(&mut conn).await?.check(amount, limit_currency, None, None)?;
 
-fn chunk_to_words(n: u64) -> String {
-    match n {
-        0..=9 => ONES[n as usize].to_string(),
-        10..=19 => TEENS[n as usize - 10].to_string(),
-        20..=99 => {
-            if n % 10 == 0 {
-                TENS[n as usize / 10].to_string()
-            } else {
-                format!("{}-{}", TENS[n as usize / 10], ONES[n as usize % 10])
-            }
-        }
-        100..=999 => {
-            if n % 100 == 0 {
-                format!("{} hundred", ONES[n as usize / 100])
-            } else {
-                format!("{} hundred and {}", ONES[n as usize / 100], chunk_to_words(n % 100))
-            }
-        }
-        _ => unreachable!(),
-    }
+    let line = amount_line(
+        amount,
+        name,
+        currency,
+        language,
+        second_language,
+        numbering_system,
+        &format.unwrap_or_default(),
+    )?;
+    serde_json::to_string(&line).map_err(|e| e.to_string())
 }
 
+/// `convert_amount_to_words` for a stored cheque, in its currency and in the
+/// numbering system set for the cheque, else for its account, else the default.
+/// Without `format` the amount line format from the settings is used. Amounts
+/// over the payment limits of the cheque's account and currency are refused.
+#[tauri::command]
+pub async fn cheque_amount_in_words(
+    cheque_id: i64,
+    language: Option<Language>,
+    second_language: Option<Language>,
+    format: Option<AmountLineFormat>,
+    pool: State<'_, SqlitePool>,
+) -> Result<String, String> {
+    let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+    let (amount_minor, client_name, currency, account_number, numbering_system): (i64, String, String, Option<String>, Option<String>) =
+        sqlx::query_as(
+            "SELECT amount_minor, client_name, currency, account_number, numbering_system FROM cheques WHERE id = ?"
+        )
+        .bind(cheque_id)
+        .fetch_optional(&mut conn)
+        .await
+        .map_err(|e| format!("Failed to load cheque: {}", e))?
+        .ok_or_else(|| format!("Cheque with ID {} not found", cheque_id))?;
 
-fn number_to_words(n: u64) -> String {
-    if n == 0 {
-        return "zero".to_string();
-    }
-    let mut words: Vec<String> = vec![];
-    let mut number: u64 = n;
-    let mut index: usize = 0;
+    let cheque_numbering = numbering_system.as_deref().map(str::parse).transpose()?;
+    let settings = load_amount_words_settings(&mut conn).await?;
+    let numbering = settings.numbering_for(cheque_numbering, account_number.as_deref());
+    let format = format.unwrap_or(settings.amount_line);
 
-    while number > 0 {
-        if number % 1000 != 0 {
-            words.insert(0, format!(
-                "{} {}",
-                chunk_to_words(number % 1000),
-                THOUSANDS[index]
-            ).trim().to_string());
-        }
-        number /= 1000;
-        index += 1;
-    }
-    words.join(" ")
+    let cheque_currency = find_currency(&currency).unwrap_or_else(default_currency);
+    let amount = cheque_currency.from_minor_units(amount_minor);
+    load_payment_limits(&mut conn).await?.check(amount, cheque_currency, account_number.as_deref(), None)?;
+    let line = amount_line(amount, &client_name, Some(&currency), language, second_language, Some(numbering), &format)?;
+    serde_json::to_string(&line).map_err(|e| e.to_string())
 }
 
-
-#[tauri::command]
-pub fn convert_amount_to_words(amount: f64, name: &str) -> Result<String, String> {
-    if amount > MAX_POSSIBLE_CHEQUE_PAYMENT {
-        return Err("Amount exceeds the limit of 25 million.".to_string());
-    }
-    if amount < 0.0 {
+/// Amount line for a payment of `amount` to `name`, laid out by `format`
+pub fn amount_line(
+    amount: Decimal,
+    name: &str,
+    currency: Option<&str>,
+    language: Option<Language>,
+    second_language: Option<Language>,
+    numbering_system: Option<NumberingSystem>,
+    format: &AmountLineFormat,
+) -> Result<AmountLine, String> {
+    if amount < Decimal::ZERO {
         return Err("Negative amounts are not allowed.".to_string());
     }
-    if amount.is_infinite() {
-        return Err("Amount cannot be infinite.".to_string());
-    }
-    if amount.is_nan() {
-        return Err("Amount is not a number.".to_string());
+    format.validate()?;
+    let currency = parse_currency(currency.unwrap_or(DEFAULT_CURRENCY))?;
+    let language = language.unwrap_or_default();
+    let numbering = numbering_system.unwrap_or_default();
+    let mut words = amount_in_words(amount, currency, language, numbering, format)?;
+    if let Some(second_language) = second_language.filter(|second| *second != language) {
+        words = format!("{} / {}", words, amount_in_words(amount, currency, second_language, numbering, format)?);
     }
-    let rounded_amount = (amount * 100.0).round() / 100.0;
-    if (amount - rounded_amount).abs() > f64::EPSILON {
-        return Err("Amount has more than two decimal places.".to_string());
-    } 
-    let whole: u64 = amount as u64;
-    let fractional: u64 = ((amount - whole as f64) * 100.0).round() as u64;
-
-    let words: String = number_to_words(whole);
-    let cents: String = number_to_words(fractional);
-
-    let dollar_word: &str = if whole == 1 { "Dollar" } else { "Dollars" };
-    let cent_word: &str = if fractional == 1 { "Cent" } else { "Cents" };
 
-    Ok(format!("Payee: {}\nAmount: {} {} and {} {}", name, words, dollar_word, cents, cent_word))
+    let lines = format.lay_out(&words)?;
+    Ok(AmountLine { payee: name.to_string(), words, lines })
 }
 
+/// `amount` spelled out with the currency's unit names in the style of
+/// `format`, such as "One Hundred Dollars and Zero Cents" or
+/// "ONE HUNDRED DOLLARS AND 00/100 ONLY"
+pub fn amount_in_words(
+    amount: Decimal,
+    currency: &Currency,
+    language: Language,
+    numbering: NumberingSystem,
+    format: &AmountLineFormat,
+) -> Result<String, String> {
+    let (whole, fractional) = currency.split_amount(amount)?;
+    let speller = language.speller(numbering);
+    let units = currency.units(language);
+    let mut words = speller.count_units(whole, &units.major_unit);
 
+    if let Some(minor_unit) = &units.minor_unit {
+        let minor = match format.minor_units {
+            MinorUnitStyle::Words => speller.count_units(fractional, minor_unit),
+            MinorUnitStyle::Fraction => format!(
+                "{:0digits$}/{}",
+                fractional,
+                10u64.pow(currency.minor_digits),
+                digits = currency.minor_digits as usize
+            ),
+        };
+        words = format!("{} {} {}", words, speller.and_word(), minor);
+    }
+    if format.only {
+        words = format!("{} {}", words, speller.only_word());
+    }
+    Ok(format.letter_case.apply(&words))
+}
@@ -0,0 +1,64 @@
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use super::column_mapping::ChequeField;
+
+/// Fields a mailed or couriered cheque cannot be delivered without
+pub const REQUIRED_ADDRESS_FIELDS: [ChequeField; 3] = [
+    ChequeField::AddressLine1,
+    ChequeField::City,
+    ChequeField::PostalCode,
+];
+
+/// How a printed cheque reaches the payee
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryMethod {
+    Mail,
+    Pickup,
+    Courier,
+}
+
+impl DeliveryMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryMethod::Mail => "mail",
+            DeliveryMethod::Pickup => "pickup",
+            DeliveryMethod::Courier => "courier",
+        }
+    }
+
+    /// Whether the cheque is sent to the payee's address
+    pub fn requires_address(&self) -> bool {
+        matches!(self, DeliveryMethod::Mail | DeliveryMethod::Courier)
+    }
+}
+
+impl FromStr for DeliveryMethod {
+    type Err = String;
+
+    /// Accepts the stored names plus common spellings found in payment files
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let normalized: String = value
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(|c| c.to_lowercase())
+            .collect();
+        match normalized.as_str() {
+            "mail" | "post" | "postal" | "postalmail" => Ok(DeliveryMethod::Mail),
+            "pickup" | "collect" | "collection" | "counter" => Ok(DeliveryMethod::Pickup),
+            "courier" => Ok(DeliveryMethod::Courier),
+            _ => Err(format!("Unknown delivery method '{}'; use mail, pickup or courier", value.trim())),
+        }
+    }
+}
+
+/// Mailing address printed in the envelope window
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PayeeAddress {
+    pub address_line1: Option<String>,
+    pub address_line2: Option<String>,
+    pub city: Option<String>,
+    pub region: Option<String>,
+    pub postal_code: Option<String>,
+    pub country: Option<String>,
+}
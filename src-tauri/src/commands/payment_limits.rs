@@ -0,0 +1,339 @@
+use std::collections::HashMap;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::SqliteConnection;
+use super::app_settings::get_setting;
+use super::currency::{find_currency, parse_currency, Currency, CURRENCIES};
+use super::import_parser::ImportOptions;
+use super::permissions::user_role;
+
+pub const PAYMENT_LIMITS_KEY: &str = "payment_limits";
+
+/// Largest cheque amount allowed in each currency when no limit has been
+/// configured for it
+pub const DEFAULT_MAX_CHEQUE_AMOUNT: i64 = 25_000_000;
+
+/// Limits by ISO 4217 currency code, each in major units of its currency
+pub type CurrencyLimits = HashMap<String, Decimal>;
+
+/// Largest amounts a cheque may be written for. A limit only applies to cheques
+/// in its own currency, and a cheque must respect every limit that applies to it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PaymentLimits {
+    /// Limit for every cheque, by currency. Cheques in a currency without one
+    /// are refused, so no currency is ever unlimited by omission.
+    pub max_amount: CurrencyLimits,
+    /// Limits for cheques paid from an account, by account number and currency
+    pub account_limits: HashMap<String, CurrencyLimits>,
+    /// Limits for cheques imported or approved by users of a role, such as
+    /// "user", by currency
+    pub role_limits: HashMap<String, CurrencyLimits>,
+}
+
+impl Default for PaymentLimits {
+    fn default() -> Self {
+        PaymentLimits {
+            max_amount: CURRENCIES
+                .iter()
+                .map(|currency| (currency.code.to_string(), Decimal::from(DEFAULT_MAX_CHEQUE_AMOUNT)))
+                .collect(),
+            account_limits: HashMap::new(),
+            role_limits: HashMap::new(),
+        }
+    }
+}
+
+impl PaymentLimits {
+    pub fn validate(&self) -> Result<(), String> {
+        validate_currency_limits(&self.max_amount, "Maximum cheque amount")?;
+        let mut accounts: Vec<&str> = Vec::new();
+        for (account, limits) in &self.account_limits {
+            let account = account.trim();
+            if account.is_empty() {
+                return Err("Enter the account number for each account limit".to_string());
+            }
+            if accounts.contains(&account) {
+                return Err(format!("Account {} has limits entered more than once", account));
+            }
+            accounts.push(account);
+            validate_currency_limits(limits, &format!("Limit for '{}'", account))?;
+        }
+        let mut roles: Vec<&str> = Vec::new();
+        for (role, limits) in &self.role_limits {
+            let role = role.trim();
+            if role.is_empty() {
+                return Err("Enter the role for each role limit".to_string());
+            }
+            if roles.contains(&role) {
+                return Err(format!("Role {} has limits entered more than once", role));
+            }
+            roles.push(role);
+            validate_currency_limits(limits, &format!("Limit for '{}'", role))?;
+        }
+        Ok(())
+    }
+
+    /// Copy with account numbers and roles trimmed and currency codes as
+    /// registered, for storing once `validate` has passed
+    pub fn normalized(self) -> Self {
+        let normalize = |limits: CurrencyLimits| -> CurrencyLimits {
+            limits
+                .into_iter()
+                .map(|(code, limit)| (find_currency(&code).map_or(code, |currency| currency.code.to_string()), limit))
+                .collect()
+        };
+        PaymentLimits {
+            max_amount: normalize(self.max_amount),
+            account_limits: self
+                .account_limits
+                .into_iter()
+                .map(|(account, limits)| (account.trim().to_string(), normalize(limits)))
+                .collect(),
+            role_limits: self
+                .role_limits
+                .into_iter()
+                .map(|(role, limits)| (role.trim().to_string(), normalize(limits)))
+                .collect(),
+        }
+    }
+
+    /// Refuse `amount` of `currency` when it exceeds the overall limit, the
+    /// limit of its account or the limit of `role` in that currency, naming the
+    /// lowest limit exceeded
+    pub fn check(
+        &self,
+        amount: Decimal,
+        currency: &Currency,
+        account_number: Option<&str>,
+        role: Option<&str>,
+    ) -> Result<(), String> {
+        let role_limit = role
+            .and_then(|role| Some((role, self.role_limits.get(role)?.get(currency.code)?)))
+            .map(|(role, limit)| (*limit, format!("the limit for the {} role", role)));
+        self.check_within(amount, currency, account_number, role_limit)
+    }
+
+    /// `check` for a cheque whose user is not known, which must be within the
+    /// lowest limit of any role in its currency
+    pub fn check_strictest_role(&self, amount: Decimal, currency: &Currency, account_number: Option<&str>) -> Result<(), String> {
+        let role_limit = self
+            .role_limits
+            .iter()
+            .filter_map(|(role, limits)| Some((role, limits.get(currency.code)?)))
+            .min_by(|(a_role, a_limit), (b_role, b_limit)| a_limit.cmp(b_limit).then(a_role.cmp(b_role)))
+            .map(|(role, limit)| (*limit, format!("the strictest role limit ({})", role)));
+        self.check_within(amount, currency, account_number, role_limit)
+    }
+
+    fn check_within(
+        &self,
+        amount: Decimal,
+        currency: &Currency,
+        account_number: Option<&str>,
+        role_limit: Option<(Decimal, String)>,
+    ) -> Result<(), String> {
+        let max_amount = self.max_amount.get(currency.code).ok_or_else(|| {
+            format!("No maximum cheque amount is set for {}; add one to the payment limits first", currency.code)
+        })?;
+        let mut applicable: Vec<(Decimal, String)> = vec![(*max_amount, "the maximum cheque amount".to_string())];
+        if let Some((account, limit)) = account_number
+            .and_then(|account| self.account_limits.get_key_value(account.trim()))
+            .and_then(|(account, limits)| Some((account, limits.get(currency.code)?)))
+        {
+            applicable.push((*limit, format!("the limit for account {}", account)));
+        }
+        applicable.extend(role_limit);
+
+        match applicable.into_iter().filter(|(limit, _)| amount > *limit).min_by_key(|(limit, _)| *limit) {
+            Some((limit, name)) => Err(format!("Amount {} {} exceeds {} of {} {}", amount, currency.code, name, limit, currency.code)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Every code in `limits` must be a supported currency, listed once, with a
+/// limit above zero. `name` says whose limits they are in error messages.
+fn validate_currency_limits(limits: &CurrencyLimits, name: &str) -> Result<(), String> {
+    let mut seen: Vec<&str> = Vec::new();
+    for (code, limit) in limits {
+        let currency = parse_currency(code)?;
+        if seen.contains(&currency.code) {
+            return Err(format!("{} in {} is entered more than once", name, currency.code));
+        }
+        seen.push(currency.code);
+        if *limit <= Decimal::ZERO {
+            return Err(format!("{} in {} must be greater than zero", name, currency.code));
+        }
+    }
+    Ok(())
+}
+
+/// Payment limits that apply to one user's cheques
+#[derive(Debug, Clone, Default)]
+pub struct UserPaymentLimits {
+    pub limits: PaymentLimits,
+    /// Role of the user; without one the lowest limit of any role applies
+    pub role: Option<String>,
+}
+
+impl UserPaymentLimits {
+    pub fn check(&self, amount: Decimal, currency: &Currency, account_number: Option<&str>) -> Result<(), String> {
+        match &self.role {
+            Some(role) => self.limits.check(amount, currency, account_number, Some(role)),
+            None => self.limits.check_strictest_role(amount, currency, account_number),
+        }
+    }
+}
+
+pub async fn load_payment_limits(conn: &mut SqliteConnection) -> Result<PaymentLimits, String> {
+    Ok(get_setting(conn, PAYMENT_LIMITS_KEY).await?.unwrap_or_default())
+}
+
+/// Payment limits for cheques imported or approved by `user_id`. Without a
+/// user, as for hot folder imports, the strictest role limit applies.
+pub async fn load_user_payment_limits(
+    conn: &mut SqliteConnection,
+    user_id: Option<i64>,
+) -> Result<UserPaymentLimits, String> {
+    let limits = load_payment_limits(&mut *conn).await?;
+    let role = match user_id {
+        Some(user_id) => Some(
+            user_role(&mut *conn, user_id)
+                .await?
+                .ok_or_else(|| format!("User with ID {} not found", user_id))?,
+        ),
+        None => None,
+    };
+    Ok(UserPaymentLimits { limits, role })
+}
+
+/// Copy of `options` carrying the payment limits of the importing user, so
+/// each row is checked against them as it is validated
+pub async fn with_payment_limits(conn: &mut SqliteConnection, options: &ImportOptions) -> Result<ImportOptions, String> {
+    let payment_limits = load_user_payment_limits(conn, options.user_id).await?;
+    Ok(ImportOptions { payment_limits: Some(payment_limits), ..options.clone() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usd() -> &'static Currency {
+        find_currency("USD").unwrap()
+    }
+
+    fn jpy() -> &'static Currency {
+        find_currency("JPY").unwrap()
+    }
+
+    fn limits() -> PaymentLimits {
+        PaymentLimits {
+            max_amount: HashMap::from([
+                ("USD".to_string(), Decimal::from(10_000)),
+                ("JPY".to_string(), Decimal::from(1_500_000)),
+            ]),
+            account_limits: HashMap::from([("ACC-1".to_string(), HashMap::from([("USD".to_string(), Decimal::from(5_000))]))]),
+            role_limits: HashMap::from([
+                ("user".to_string(), HashMap::from([("USD".to_string(), Decimal::from(1_000))])),
+                ("manager".to_string(), HashMap::from([("USD".to_string(), Decimal::from(8_000))])),
+            ]),
+        }
+    }
+
+    #[test]
+    fn names_the_lowest_limit_exceeded() {
+        let limits = limits();
+        assert!(limits.check(Decimal::from(10_000), usd(), None, None).is_ok());
+        assert_eq!(
+            limits.check(Decimal::from(10_001), usd(), None, None).unwrap_err(),
+            "Amount 10001 USD exceeds the maximum cheque amount of 10000 USD"
+        );
+        assert_eq!(
+            limits.check(Decimal::from(6_000), usd(), Some(" ACC-1 "), Some("manager")).unwrap_err(),
+            "Amount 6000 USD exceeds the limit for account ACC-1 of 5000 USD"
+        );
+        assert_eq!(
+            limits.check(Decimal::from(2_000), usd(), Some("ACC-1"), Some("user")).unwrap_err(),
+            "Amount 2000 USD exceeds the limit for the user role of 1000 USD"
+        );
+        assert!(limits.check(Decimal::from(6_000), usd(), Some("ACC-2"), Some("manager")).is_ok());
+        assert!(limits.check(Decimal::from(9_000), usd(), None, Some("admin")).is_ok());
+    }
+
+    #[test]
+    fn applies_limits_only_in_their_currency() {
+        let limits = limits();
+        // Account and role limits in dollars say nothing about yen
+        assert!(limits.check(Decimal::from(1_000_000), jpy(), Some("ACC-1"), Some("user")).is_ok());
+        assert_eq!(
+            limits.check(Decimal::from(2_000_000), jpy(), None, None).unwrap_err(),
+            "Amount 2000000 JPY exceeds the maximum cheque amount of 1500000 JPY"
+        );
+        let eur = find_currency("EUR").unwrap();
+        assert_eq!(
+            limits.check(Decimal::ONE, eur, None, None).unwrap_err(),
+            "No maximum cheque amount is set for EUR; add one to the payment limits first"
+        );
+    }
+
+    #[test]
+    fn defaults_allow_every_currency() {
+        let limits = PaymentLimits::default();
+        for currency in CURRENCIES {
+            let max = Decimal::from(DEFAULT_MAX_CHEQUE_AMOUNT);
+            assert!(limits.check(max, currency, None, None).is_ok(), "{}", currency.code);
+            assert!(limits.check(max + Decimal::ONE, currency, None, None).is_err(), "{}", currency.code);
+        }
+        let eur = find_currency("EUR").unwrap();
+        assert!(limits.check(Decimal::new(123_456, 2), eur, Some("ACC-1"), Some("user")).is_ok());
+        assert!(UserPaymentLimits { limits: limits.clone(), role: None }.check(Decimal::from(900_000), jpy(), None).is_ok());
+    }
+
+    #[test]
+    fn applies_the_strictest_role_limit_without_a_user() {
+        let limits = UserPaymentLimits { limits: limits(), role: None };
+        assert!(limits.check(Decimal::from(1_000), usd(), None).is_ok());
+        assert_eq!(
+            limits.check(Decimal::from(1_500), usd(), None).unwrap_err(),
+            "Amount 1500 USD exceeds the strictest role limit (user) of 1000 USD"
+        );
+        assert!(limits.check(Decimal::from(1_000_000), jpy(), None).is_ok());
+        let manager = UserPaymentLimits { limits: limits.limits.clone(), role: Some("manager".to_string()) };
+        assert!(manager.check(Decimal::from(1_500), usd(), None).is_ok());
+    }
+
+    #[test]
+    fn validates_and_normalizes_limits() {
+        assert!(limits().validate().is_ok());
+
+        let mut zero = limits();
+        zero.role_limits.insert("clerk".to_string(), HashMap::from([("USD".to_string(), Decimal::ZERO)]));
+        assert_eq!(zero.validate().unwrap_err(), "Limit for 'clerk' in USD must be greater than zero");
+
+        let mut unknown = limits();
+        unknown.max_amount.insert("XYZ".to_string(), Decimal::ONE);
+        assert!(unknown.validate().unwrap_err().starts_with("Unknown currency 'XYZ'"));
+
+        let mut twice = limits();
+        twice.max_amount.insert("usd".to_string(), Decimal::ONE);
+        assert_eq!(twice.validate().unwrap_err(), "Maximum cheque amount in USD is entered more than once");
+
+        let mut padded = limits();
+        padded.account_limits.insert("ACC-1 ".to_string(), HashMap::from([("USD".to_string(), Decimal::ONE)]));
+        assert_eq!(padded.validate().unwrap_err(), "Account ACC-1 has limits entered more than once");
+
+        let mut padded = limits();
+        padded.role_limits.insert(" user".to_string(), HashMap::from([("USD".to_string(), Decimal::ONE)]));
+        assert_eq!(padded.validate().unwrap_err(), "Role user has limits entered more than once");
+
+        let lower_case = PaymentLimits {
+            max_amount: HashMap::from([("eur".to_string(), Decimal::ONE)]),
+            account_limits: HashMap::from([(" ACC-9 ".to_string(), HashMap::from([(" gbp".to_string(), Decimal::ONE)]))]),
+            role_limits: HashMap::new(),
+        };
+        let normalized = lower_case.normalized();
+        assert!(normalized.max_amount.contains_key("EUR"));
+        assert!(normalized.account_limits["ACC-9"].contains_key("GBP"));
+    }
+}
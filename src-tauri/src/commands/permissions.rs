@@ -0,0 +1,27 @@
+use sqlx::SqliteConnection;
+
+/// Role of `user_id`, or `None` when there is no such user
+pub async fn user_role(conn: &mut SqliteConnection, user_id: i64) -> Result<Option<String>, String> {
+    Ok(sqlx::query_scalar::<_, Option<String>>("SELECT role FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| format!("Failed to look up user role: {}", e))?
+        .flatten())
+}
+
+/// Fail unless `user_id` belongs to an administrator. `action` describes what
+/// was attempted and is used in the error message.
+pub async fn require_admin(
+    conn: &mut SqliteConnection,
+    user_id: Option<i64>,
+    action: &str,
+) -> Result<(), String> {
+    let user_id = user_id.ok_or_else(|| format!("Only an administrator can {}", action))?;
+
+    match user_role(conn, user_id).await?.as_deref() {
+        Some("admin") => Ok(()),
+        Some(_) => Err(format!("Only an administrator can {}", action)),
+        None => Err(format!("User with ID {} not found", user_id)),
+    }
+}
@@ -0,0 +1,200 @@
+use calamine::{Data, Ods, Range, Reader, Sheets, Xls, Xlsb, Xlsx};
+use std::io::Cursor;
+use super::column_mapping::column_letters_to_index;
+use super::delimited_text::read_delimited;
+use super::encrypted_workbook::{is_encrypted_workbook, password_required_message};
+
+const ZIP_SIGNATURE: &[u8] = b"PK\x03\x04";
+pub const OLE_SIGNATURE: &[u8] = b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1";
+const ODS_MIMETYPE: &[u8] = b"application/vnd.oasis.opendocument.spreadsheet";
+
+/// Upload formats the importer can read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    Xlsx,
+    Xlsb,
+    Xls,
+    Ods,
+    Delimited,
+}
+
+impl FileFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FileFormat::Xlsx => "xlsx",
+            FileFormat::Xlsb => "xlsb",
+            FileFormat::Xls => "xls",
+            FileFormat::Ods => "ods",
+            FileFormat::Delimited => "csv",
+        }
+    }
+}
+
+/// Work out the file format from its content, using the extension only to
+/// confirm that an unrecognised binary is really meant to be delimited text
+pub fn detect_format(data: &[u8], file_name: &str) -> Result<FileFormat, String> {
+    if data.starts_with(OLE_SIGNATURE) {
+        // Password-protected XLSX files are compound documents too, and must be
+        // decrypted with `unlock_upload` before they can be read
+        if is_encrypted_workbook(data) {
+            return Err(password_required_message(file_name));
+        }
+        return Ok(FileFormat::Xls);
+    }
+
+    if data.starts_with(ZIP_SIGNATURE) {
+        // Zip local headers store entry names uncompressed, so the package
+        // type can be recognised without inflating anything
+        if contains(data, ODS_MIMETYPE) {
+            return Ok(FileFormat::Ods);
+        }
+        if contains(data, b"xl/workbook.bin") {
+            return Ok(FileFormat::Xlsb);
+        }
+        if contains(data, b"xl/workbook.xml") || contains(data, b"[Content_Types].xml") {
+            return Ok(FileFormat::Xlsx);
+        }
+        return Err(format!("'{}' is a zip archive but not a spreadsheet workbook", file_name));
+    }
+
+    if looks_like_text(data) || has_extension(file_name, &["csv", "tsv", "txt"]) {
+        return Ok(FileFormat::Delimited);
+    }
+
+    Err(format!(
+        "Unsupported file format for '{}'. Supported formats: XLSX, XLSM, XLSB, XLS, ODS, CSV and TSV",
+        file_name
+    ))
+}
+
+/// Open an uploaded workbook of any supported binary format
+pub fn open_workbook<'a>(data: &'a [u8], file_name: &str) -> Result<Sheets<Cursor<&'a [u8]>>, String> {
+    let format = detect_format(data, file_name)?;
+    let cursor = Cursor::new(data);
+    let workbook = match format {
+        FileFormat::Xlsx => Xlsx::new(cursor).map(Sheets::Xlsx).map_err(|e| e.to_string()),
+        FileFormat::Xlsb => Xlsb::new(cursor).map(Sheets::Xlsb).map_err(|e| e.to_string()),
+        FileFormat::Xls => Xls::new(cursor).map(Sheets::Xls).map_err(|e| e.to_string()),
+        FileFormat::Ods => Ods::new(cursor).map(Sheets::Ods).map_err(|e| e.to_string()),
+        FileFormat::Delimited => {
+            return Err(format!("'{}' is a delimited text file, not a workbook", file_name));
+        }
+    };
+    workbook.map_err(|e| format!("Failed to open '{}' as {}: {}", file_name, format.as_str(), e))
+}
+
+/// Names of the sheets in an uploaded file, in workbook order. Delimited text
+/// has a single implicit sheet named after the file.
+pub fn sheet_names(data: &[u8], file_name: &str) -> Result<Vec<String>, String> {
+    if detect_format(data, file_name)? == FileFormat::Delimited {
+        return Ok(vec![delimited_sheet_name(file_name)]);
+    }
+    Ok(open_workbook(data, file_name)?.sheet_names())
+}
+
+/// Read the named sheets from an uploaded workbook, CSV or TSV file
+pub fn read_sheets(
+    data: &[u8],
+    file_name: &str,
+    names: &[String],
+) -> Result<Vec<(String, Range<Data>)>, String> {
+    if detect_format(data, file_name)? == FileFormat::Delimited {
+        let range = read_delimited(data, file_name)?;
+        return Ok(vec![(delimited_sheet_name(file_name), range)]);
+    }
+
+    let mut workbook = open_workbook(data, file_name)?;
+    names
+        .iter()
+        .map(|name| {
+            workbook
+                .worksheet_range(name)
+                .map(|range| (name.clone(), range))
+                .map_err(|e| format!("Failed to read worksheet '{}': {}", name, e))
+        })
+        .collect()
+}
+
+/// Value of the single cell named by a workbook defined name or by a reference
+/// such as `B12`, `Summary!$B$12` or `'Batch totals'!B12`. References without
+/// a sheet read the first sheet.
+pub fn read_cell_reference(data: &[u8], file_name: &str, reference: &str) -> Result<Data, String> {
+    let unknown = || format!("'{}' is not a defined name or cell reference in '{}'", reference, file_name);
+
+    let mut workbook = match detect_format(data, file_name)? {
+        FileFormat::Delimited => None,
+        _ => Some(open_workbook(data, file_name)?),
+    };
+    let target = workbook
+        .as_ref()
+        .and_then(|workbook| {
+            workbook
+                .defined_names()
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(reference.trim()))
+                .map(|(_, formula)| formula.trim_start_matches('=').to_string())
+        })
+        .unwrap_or_else(|| reference.trim().to_string());
+
+    let (sheet, address) = match target.rsplit_once('!') {
+        Some((sheet, address)) => (Some(sheet.trim_matches('\'').replace("''", "'")), address),
+        None => (None, target.as_str()),
+    };
+    let position = parse_cell_address(address).ok_or_else(unknown)?;
+
+    let range = match workbook.as_mut() {
+        None => read_delimited(data, file_name)?,
+        Some(workbook) => {
+            let sheet = match sheet {
+                Some(sheet) => sheet,
+                None => workbook.sheet_names().first().cloned().ok_or_else(unknown)?,
+            };
+            workbook
+                .worksheet_range(&sheet)
+                .map_err(|e| format!("Failed to read worksheet '{}': {}", sheet, e))?
+        }
+    };
+    Ok(range.get_value(position).cloned().unwrap_or(Data::Empty))
+}
+
+/// 0-based (row, column) of an A1-style address; absolute markers are ignored
+/// and a single-cell range such as `$B$2:$B$2` gives its first cell
+fn parse_cell_address(address: &str) -> Option<(u32, u32)> {
+    let first = address.split(':').next()?.replace('$', "");
+    let split = first.find(|c: char| c.is_ascii_digit())?;
+    let (letters, digits) = first.split_at(split);
+    let row: u32 = digits.parse().ok()?;
+    let col = column_letters_to_index(letters)?;
+    (row >= 1).then(|| (row - 1, col))
+}
+
+fn delimited_sheet_name(file_name: &str) -> String {
+    file_name
+        .rsplit_once('.')
+        .map(|(stem, _)| stem)
+        .unwrap_or(file_name)
+        .to_string()
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|window| window == needle)
+}
+
+fn has_extension(file_name: &str, extensions: &[&str]) -> bool {
+    file_name
+        .rsplit_once('.')
+        .map(|(_, ext)| extensions.iter().any(|e| ext.eq_ignore_ascii_case(e)))
+        .unwrap_or(false)
+}
+
+/// Binary formats contain control bytes early on; text (with or without a BOM) does not
+fn looks_like_text(data: &[u8]) -> bool {
+    if data.starts_with(b"\xEF\xBB\xBF") || data.starts_with(b"\xFF\xFE") || data.starts_with(b"\xFE\xFF") {
+        return true;
+    }
+    !data.is_empty()
+        && data
+            .iter()
+            .take(4096)
+            .all(|&b| b >= 0x20 || matches!(b, b'\t' | b'\n' | b'\r' | 0x0C))
+}
@@ -8,7 +8,14 @@ pub struct Document {
     pub id: i64,
     pub file_name: String,
     pub file_data: Vec<u8>,
+    pub content_hash: Option<String>,
     pub created_at: NaiveDateTime,
+    /// "verified", "unsigned" or "invalid"; absent for uploads made before signatures were checked
+    pub signature_status: Option<String>,
+    pub signature_key: Option<String>,
+    pub signature_algorithm: Option<String>,
+    pub signature: Option<String>,
+    pub signature_detail: Option<String>,
 }
 
 /// Cheque with associated document information (for joins)
@@ -20,7 +27,8 @@ pub struct ChequeWithDocument {
     pub is_locked: Option<i64>,
     pub cheque_id: Option<i64>,
     pub cheque_number: Option<String>,
-    pub amount: Option<f64>,
+    /// Amount in minor units of `currency`, such as cents
+    pub amount_minor: Option<i64>,
     pub client_name: Option<String>,
     pub status: Option<String>,
     pub issue_date: Option<String>,
@@ -30,6 +38,24 @@ pub struct ChequeWithDocument {
     pub first_signature_user_id: Option<i64>,
     pub second_signature_user_id: Option<i64>,
     pub print_count: Option<i64>,
+    pub account_number: Option<String>,
+    pub duplicate_flag: Option<String>,
+    pub duplicate_override_reason: Option<String>,
+    pub source_sheet: Option<String>,
+    pub source_row: Option<i64>,
+    pub memo: Option<String>,
+    pub address_line1: Option<String>,
+    pub address_line2: Option<String>,
+    pub city: Option<String>,
+    pub region: Option<String>,
+    pub postal_code: Option<String>,
+    pub country: Option<String>,
+    /// "mail", "pickup" or "courier"
+    pub delivery_method: Option<String>,
+    /// ISO 4217 currency code
+    pub currency: Option<String>,
+    /// "international", "british", "american" or "indian"; empty to follow the account
+    pub numbering_system: Option<String>,
 }
 
 /// Individual cheque record
@@ -38,15 +64,114 @@ pub struct Cheque {
     pub id: i64,
     pub document_id: i64,
     pub cheque_number: String,
-    pub amount: f64,
+    /// Amount in minor units of `currency`, such as cents
+    pub amount_minor: i64,
     pub client_name: String,
+    pub account_number: Option<String>,
     pub status: String,
     pub issue_date: Option<String>,
     pub date_field: Option<String>,
     pub remarks: Option<String>,
+    pub duplicate_flag: Option<String>,
+    pub duplicate_override_reason: Option<String>,
+    pub source_sheet: Option<String>,
+    pub source_row: Option<i64>,
+    pub memo: Option<String>,
+    pub address_line1: Option<String>,
+    pub address_line2: Option<String>,
+    pub city: Option<String>,
+    pub region: Option<String>,
+    pub postal_code: Option<String>,
+    pub country: Option<String>,
+    /// "mail", "pickup" or "courier"
+    pub delivery_method: Option<String>,
+    /// ISO 4217 currency code
+    pub currency: String,
+    /// "international", "british", "american" or "indian"; empty to follow the account
+    pub numbering_system: Option<String>,
     pub created_at: Option<NaiveDateTime>,
 }
 
+/// Named spreadsheet layout used when importing cheques
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct MappingProfile {
+    pub id: i64,
+    pub name: String,
+    pub sheet_name: Option<String>,
+    pub header_row: i64,
+    pub column_map: String,
+    /// "auto", "point" or "comma"
+    pub decimal_separator: String,
+    pub cheque_number_digits: Option<i64>,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+/// Spreadsheet row rejected during import
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct ImportIssue {
+    pub id: i64,
+    pub document_id: i64,
+    pub sheet_name: String,
+    pub row_number: i64,
+    pub column_name: Option<String>,
+    pub field: Option<String>,
+    pub reason: String,
+    pub cell_value: Option<String>,
+    pub created_at: Option<NaiveDateTime>,
+}
+
+/// Cheque whose REAL amount was not a whole number of minor units when amounts
+/// moved to minor units, and was rounded
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct AmountConversionIssue {
+    pub id: i64,
+    pub cheque_id: i64,
+    pub cheque_number: Option<String>,
+    pub currency: String,
+    /// Amount as it was stored before the conversion
+    pub original_amount: f64,
+    /// Amount stored now, in minor units of `currency`
+    pub amount_minor: i64,
+    pub created_at: Option<NaiveDateTime>,
+}
+
+/// Upload held for review before its rows are imported
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct StagedImport {
+    pub id: i64,
+    pub file_name: String,
+    /// "staged", "committed" or "discarded"
+    pub status: String,
+    pub document_id: Option<i64>,
+    pub skipped_sheets: Option<String>,
+    pub declared_totals: Option<String>,
+    pub created_by: Option<i64>,
+    pub row_count: i64,
+    pub invalid_count: i64,
+    pub deleted_count: i64,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+/// Spreadsheet row of a staged upload. Values are JSON objects keyed by field;
+/// `original_values` is what the file held and `current_values` includes corrections.
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct StagedRow {
+    pub id: i64,
+    pub staged_import_id: i64,
+    pub sheet_name: String,
+    pub row_number: i64,
+    pub column_map: String,
+    pub original_values: String,
+    pub current_values: String,
+    pub issues: Option<String>,
+    pub is_deleted: bool,
+    pub cheque_id: Option<i64>,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
 /// Kanban board note
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
 pub struct KanbanNote {
@@ -1,7 +1,14 @@
 use sqlx::SqlitePool;
 use tauri::State;
 use serde_json::json;
-use crate::database::models::{ChequeWithDocument, DataError};
+use crate::commands::analytics_query::run_analytics_query;
+use crate::commands::currency::{default_currency, find_currency, stored_amount, DEFAULT_CURRENCY};
+use crate::commands::duplicate_payments::{
+    find_duplicate_payments, load_duplicate_rules, record_duplicate_override, PaymentCandidate,
+};
+use crate::commands::number_words::NumberingSystem;
+use crate::commands::payment_limits::load_user_payment_limits;
+use crate::database::models::{AmountConversionIssue, ChequeWithDocument, DataError};
 
 type Result<T> = std::result::Result<T, DataError>;
 
@@ -10,10 +17,13 @@ type Result<T> = std::result::Result<T, DataError>;
 pub async fn get_all_cheques(pool: State<'_, SqlitePool>) -> Result<String> {
     let records: Vec<ChequeWithDocument> = sqlx::query_as::<_, ChequeWithDocument>(
         "SELECT d.id as document_id, d.file_name, d.created_at, d.is_locked,
-                c.id as cheque_id, c.cheque_number, c.amount, c.client_name,
+                c.id as cheque_id, c.cheque_number, c.amount_minor, c.client_name,
                 c.status, c.issue_date, c.date_field, c.remarks,
                 c.current_signatures, c.first_signature_user_id, c.second_signature_user_id,
-                c.print_count
+                c.print_count, c.account_number, c.duplicate_flag, c.duplicate_override_reason,
+                c.source_sheet, c.source_row, c.memo,
+                c.address_line1, c.address_line2, c.city, c.region, c.postal_code, c.country,
+                c.delivery_method, c.currency, c.numbering_system
          FROM documents d
          LEFT JOIN cheques c ON d.id = c.document_id
          ORDER BY d.created_at DESC, c.id ASC"
@@ -30,16 +40,32 @@ pub async fn get_all_cheques(pool: State<'_, SqlitePool>) -> Result<String> {
                     "file_name": r.file_name,
                     "created_at": r.created_at,
                     "cheque_number": r.cheque_number,
-                    "amount": r.amount,
+                    "amount": r.amount_minor.map(|minor| stored_amount(minor, r.currency.as_deref().unwrap_or(DEFAULT_CURRENCY))),
+                    "amount_minor": r.amount_minor,
                     "client_name": r.client_name,
                     "status": r.status,
                     "issue_date": r.issue_date,
                     "date": r.date_field,
                     "remarks": r.remarks,
+                    "memo": r.memo,
+                    "address_line1": r.address_line1,
+                    "address_line2": r.address_line2,
+                    "city": r.city,
+                    "region": r.region,
+                    "postal_code": r.postal_code,
+                    "country": r.country,
+                    "delivery_method": r.delivery_method,
+                    "currency": r.currency,
+                    "numbering_system": r.numbering_system,
                     "current_signatures": r.current_signatures,
                     "first_signature_user_id": r.first_signature_user_id,
                     "second_signature_user_id": r.second_signature_user_id,
                     "print_count": r.print_count,
+                    "account_number": r.account_number,
+                    "duplicate_flag": r.duplicate_flag,
+                    "duplicate_override_reason": r.duplicate_override_reason,
+                    "source_sheet": r.source_sheet,
+                    "source_row": r.source_row,
                     "is_locked": r.is_locked
                 }))
             } else { None }
@@ -49,12 +75,43 @@ pub async fn get_all_cheques(pool: State<'_, SqlitePool>) -> Result<String> {
     Ok(serde_json::to_string(&response)?)
 }
 
-/// Update cheque status (Approved, Declined, Pending)
+/// Get the cheques whose amounts were rounded when amounts moved to minor
+/// units, with the amount each had before and has now
+#[tauri::command]
+pub async fn get_amount_conversion_issues(pool: State<'_, SqlitePool>) -> Result<String> {
+    let issues: Vec<AmountConversionIssue> = sqlx::query_as::<_, AmountConversionIssue>(
+        "SELECT i.id, i.cheque_id, c.cheque_number, i.currency, i.original_amount, i.amount_minor, i.created_at
+         FROM amount_conversion_issues i
+         LEFT JOIN cheques c ON c.id = i.cheque_id
+         ORDER BY i.cheque_id ASC, i.id ASC"
+    )
+    .fetch_all(pool.inner())
+    .await
+    .map_err(|e| DataError::Database(format!("Failed to fetch amount conversion issues: {}", e)))?;
+
+    let response: Vec<_> = issues.iter().map(|issue| json!({
+        "id": issue.id,
+        "cheque_id": issue.cheque_id,
+        "cheque_number": issue.cheque_number,
+        "currency": issue.currency,
+        "original_amount": issue.original_amount,
+        "amount": stored_amount(issue.amount_minor, &issue.currency),
+        "amount_minor": issue.amount_minor,
+        "created_at": issue.created_at
+    })).collect();
+
+    Ok(serde_json::to_string(&response)?)
+}
+
+/// Update cheque status (Approved, Declined, Pending). Approval is checked
+/// against the payment limits of the approving user, who is recorded as the
+/// first signatory; the change is audited under `user_id`.
 #[tauri::command]
 pub async fn update_cheque_status(
     cheque_id: i64,
     new_status: String,
     remarks: Option<String>,
+    user_id: Option<i64>,
     pool: State<'_, SqlitePool>,
 ) -> Result<()> {
     let valid_statuses = ["Approved", "Declined", "Pending"];
@@ -80,24 +137,37 @@ pub async fn update_cheque_status(
         )));
     }
 
+    if new_status == "Approved" {
+        check_payment_limits(pool.inner(), cheque_id, user_id).await?;
+        check_duplicate_payment(pool.inner(), cheque_id).await?;
+    }
+
+    let mut tx = pool.begin().await?;
+    let last_audit_id: i64 = sqlx::query_scalar("SELECT COALESCE(MAX(id), 0) FROM audit_trail")
+        .fetch_one(&mut tx)
+        .await
+        .map_err(|e| DataError::Database(format!("Failed to read audit trail: {}", e)))?;
+
     let result = if new_status == "Approved" && remarks.is_some() {
         sqlx::query!(
-            "UPDATE cheques SET status = ?, current_signatures = 1, first_signature_user_id = 1, remarks = ?,
+            "UPDATE cheques SET status = ?, current_signatures = 1, first_signature_user_id = ?, remarks = ?,
              issue_date = COALESCE(issue_date, datetime('now', 'localtime')) WHERE id = ?",
             new_status,
+            user_id,
             remarks,
             cheque_id
         )
-        .execute(pool.inner())
+        .execute(&mut tx)
         .await
     } else if new_status == "Approved" {
         sqlx::query!(
-            "UPDATE cheques SET status = ?, current_signatures = 1, first_signature_user_id = 1,
+            "UPDATE cheques SET status = ?, current_signatures = 1, first_signature_user_id = ?,
              issue_date = COALESCE(issue_date, datetime('now', 'localtime')) WHERE id = ?",
             new_status,
+            user_id,
             cheque_id
         )
-        .execute(pool.inner())
+        .execute(&mut tx)
         .await
     } else if let Some(remarks_text) = remarks {
         sqlx::query!(
@@ -106,7 +176,7 @@ pub async fn update_cheque_status(
             remarks_text,
             cheque_id
         )
-        .execute(pool.inner())
+        .execute(&mut tx)
         .await
     } else {
         sqlx::query!(
@@ -114,11 +184,139 @@ pub async fn update_cheque_status(
             new_status,
             cheque_id
         )
-        .execute(pool.inner())
+        .execute(&mut tx)
         .await
     };
 
     result.map_err(|e| DataError::Database(format!("Failed to update cheque status: {}", e)))?;
+
+    // The audit triggers record changes as made by the system; name the user
+    // who made them, or 'Unknown' when none was given
+    sqlx::query(
+        "UPDATE audit_trail SET user_id = ?, user_name = COALESCE((SELECT username FROM users WHERE id = ?), 'Unknown')
+         WHERE id > ? AND cheque_id = ?"
+    )
+    .bind(user_id)
+    .bind(user_id)
+    .bind(last_audit_id)
+    .bind(cheque_id)
+    .execute(&mut tx)
+    .await
+    .map_err(|e| DataError::Database(format!("Failed to write audit trail: {}", e)))?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Refuse to approve a cheque over the limit of its account or of the approver's
+/// role. Limits may have been lowered since the cheque was imported.
+async fn check_payment_limits(pool: &SqlitePool, cheque_id: i64, user_id: Option<i64>) -> Result<()> {
+    let mut conn = pool.acquire().await?;
+
+    let (account_number, amount_minor, currency): (Option<String>, i64, String) =
+        sqlx::query_as("SELECT account_number, amount_minor, currency FROM cheques WHERE id = ?")
+            .bind(cheque_id)
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(|e| DataError::Database(format!("Failed to load cheque: {}", e)))?;
+
+    let currency = find_currency(&currency).unwrap_or_else(default_currency);
+    let limits = load_user_payment_limits(&mut conn, user_id).await?;
+    limits
+        .check(currency.from_minor_units(amount_minor), currency, account_number.as_deref())
+        .map_err(|reason| DataError::Custom(format!("Cheque {} cannot be approved: {}", cheque_id, reason)))
+}
+
+/// Re-run the duplicate payment rules before approval. Other batches may have been
+/// imported since this cheque was, so a flag raised now is stored on the cheque.
+/// A flagged cheque always needs an override, even once the payment it matched
+/// has been declined or deleted.
+async fn check_duplicate_payment(pool: &SqlitePool, cheque_id: i64) -> Result<()> {
+    let mut conn = pool.acquire().await?;
+
+    let (account_number, cheque_number, client_name, amount_minor, currency, duplicate_flag, override_reason): (Option<String>, String, String, i64, String, Option<String>, Option<String>) =
+        sqlx::query_as(
+            "SELECT account_number, cheque_number, client_name, amount_minor, currency, duplicate_flag,
+                    duplicate_override_reason
+             FROM cheques WHERE id = ?"
+        )
+        .bind(cheque_id)
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|e| DataError::Database(format!("Failed to load cheque: {}", e)))?;
+
+    if override_reason.is_some() {
+        return Ok(());
+    }
+
+    let rules = load_duplicate_rules(&mut conn).await?;
+    let matches = find_duplicate_payments(&mut conn, &rules, &PaymentCandidate {
+        account_number: account_number.as_deref(),
+        cheque_number: &cheque_number,
+        client_name: &client_name,
+        amount: stored_amount(amount_minor, &currency),
+        amount_minor,
+        currency: &currency,
+        cheque_id: Some(cheque_id),
+    })
+    .await?;
+
+    let flag = if matches.is_empty() {
+        match duplicate_flag {
+            Some(flag) => flag,
+            None => return Ok(()),
+        }
+    } else {
+        let flag = matches.join("; ");
+        sqlx::query("UPDATE cheques SET duplicate_flag = ? WHERE id = ?")
+            .bind(&flag)
+            .bind(cheque_id)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| DataError::Database(format!("Failed to flag duplicate cheque: {}", e)))?;
+        flag
+    };
+
+    Err(DataError::Custom(format!(
+        "Cheque {} may be a duplicate payment and needs an override with a reason before approval: {}",
+        cheque_id, flag
+    )))
+}
+
+/// Override a duplicate payment flag so the cheque can be approved
+#[tauri::command]
+pub async fn override_duplicate_payment(
+    cheque_id: i64,
+    reason: String,
+    user_id: Option<i64>,
+    pool: State<'_, SqlitePool>,
+) -> Result<()> {
+    if reason.trim().is_empty() {
+        return Err(DataError::Custom("Override reason cannot be empty".to_string()));
+    }
+
+    let mut tx = pool.begin().await?;
+
+    let flag: Option<Option<String>> = sqlx::query_scalar("SELECT duplicate_flag FROM cheques WHERE id = ?")
+        .bind(cheque_id)
+        .fetch_optional(&mut tx)
+        .await
+        .map_err(|e| DataError::Database(format!("Failed to load cheque: {}", e)))?;
+
+    let flag = match flag {
+        None => return Err(DataError::Custom(format!("Cheque with ID {} not found", cheque_id))),
+        Some(None) => {
+            return Err(DataError::Custom(format!(
+                "Cheque {} is not flagged as a duplicate payment",
+                cheque_id
+            )))
+        }
+        Some(Some(flag)) => flag,
+    };
+
+    record_duplicate_override(&mut tx, cheque_id, &flag, reason.trim(), user_id).await?;
+    tx.commit().await?;
+
     Ok(())
 }
 
@@ -153,6 +351,35 @@ pub async fn update_cheque_issue_date(
     Ok(())
 }
 
+/// Set how a cheque's amount is written in words; `None` follows its account's setting
+#[tauri::command]
+pub async fn update_cheque_numbering_system(
+    cheque_id: i64,
+    numbering_system: Option<NumberingSystem>,
+    pool: State<'_, SqlitePool>,
+) -> Result<()> {
+    let cheque_exists = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM cheques WHERE id = ?"
+    )
+    .bind(cheque_id)
+    .fetch_one(pool.inner())
+    .await
+    .map_err(|e| DataError::Database(format!("Failed to verify cheque exists: {}", e)))?;
+
+    if cheque_exists == 0 {
+        return Err(DataError::Custom(format!("Cheque with ID {} not found", cheque_id)));
+    }
+
+    sqlx::query("UPDATE cheques SET numbering_system = ? WHERE id = ?")
+        .bind(numbering_system.map(|system| system.as_str()))
+        .bind(cheque_id)
+        .execute(pool.inner())
+        .await
+        .map_err(|e| DataError::Database(format!("Failed to update cheque numbering system: {}", e)))?;
+
+    Ok(())
+}
+
 /// Update decline reason for rejected cheques
 #[tauri::command]
 pub async fn update_decline_reason(
@@ -212,43 +439,12 @@ pub async fn increment_print_count(
     Ok(())
 }
 
-/// Execute custom SQL query for analytics (use with caution)
+/// Execute a custom read-only SELECT for analytics; see `run_analytics_query` for what it may read
 #[tauri::command]
 pub async fn execute_dynamic_query(
     sql_query: String,
     pool: State<'_, SqlitePool>,
 ) -> Result<String> {
-    if sql_query.trim().is_empty() {
-        return Err(DataError::Custom("SQL query cannot be empty".to_string()));
-    }
-
-    let dangerous_keywords = ["DROP", "DELETE", "TRUNCATE", "ALTER"];
-    let uppercase_query = sql_query.to_uppercase();
-    for keyword in &dangerous_keywords {
-        if uppercase_query.contains(keyword) {
-            return Err(DataError::Custom(format!(
-                "Query contains dangerous keyword '{}' and is not allowed",
-                keyword
-            )));
-        }
-    }
-
-    let rows = sqlx::query(&sql_query)
-        .fetch_all(pool.inner())
-        .await
-        .map_err(|e| DataError::Database(format!("Query execution failed: {}", e)))?;
-
-    let result: Vec<serde_json::Value> = rows
-        .iter()
-        .map(|row| {
-            let mut obj = serde_json::Map::new();
-            for (i, col) in row.columns().iter().enumerate() {
-                let value: Option<String> = row.try_get(i).ok();
-                obj.insert(col.name().to_string(), json!(value));
-            }
-            json!(obj)
-        })
-        .collect();
-
+    let result = run_analytics_query(pool.inner(), &sql_query).await?;
     Ok(serde_json::to_string(&result)?)
 }
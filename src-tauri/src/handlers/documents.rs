@@ -4,7 +4,7 @@ use serde_json::json;
 use std::path::PathBuf;
 use tokio::fs;
 use tauri::{path::BaseDirectory, AppHandle, Manager};
-use crate::database::models::DataError;
+use crate::database::models::{DataError, ImportIssue};
 
 type Result<T> = std::result::Result<T, DataError>;
 
@@ -12,7 +12,9 @@ type Result<T> = std::result::Result<T, DataError>;
 #[tauri::command]
 pub async fn get_all_documents(pool: State<'_, SqlitePool>) -> Result<String> {
     let documents = sqlx::query!(
-        "SELECT id, file_name, file_data, created_at, is_locked FROM documents"
+        "SELECT id, file_name, file_data, content_hash, created_at, is_locked,
+                signature_status, signature_key, signature_algorithm, signature_detail
+         FROM documents"
     )
     .fetch_all(pool.inner())
     .await?;
@@ -24,8 +26,13 @@ pub async fn get_all_documents(pool: State<'_, SqlitePool>) -> Result<String> {
                 "id": doc.id,
                 "file_name": doc.file_name,
                 "file_data": doc.file_data,
+                "content_hash": doc.content_hash,
                 "created_at": doc.created_at,
-                "is_locked": doc.is_locked
+                "is_locked": doc.is_locked,
+                "signature_status": doc.signature_status,
+                "signature_key": doc.signature_key,
+                "signature_algorithm": doc.signature_algorithm,
+                "signature_detail": doc.signature_detail
             })
         })
         .collect();
@@ -154,6 +161,24 @@ pub async fn lock_document(
     Ok(())
 }
 
+/// Get the rows rejected when a document was imported
+#[tauri::command]
+pub async fn get_import_issues(
+    document_id: i64,
+    pool: State<'_, SqlitePool>,
+) -> Result<String> {
+    let issues: Vec<ImportIssue> = sqlx::query_as::<_, ImportIssue>(
+        "SELECT id, document_id, sheet_name, row_number, column_name, field, reason, cell_value, created_at
+         FROM import_issues WHERE document_id = ? ORDER BY sheet_name ASC, row_number ASC, id ASC"
+    )
+    .bind(document_id)
+    .fetch_all(pool.inner())
+    .await
+    .map_err(|e| DataError::Database(format!("Failed to fetch import issues: {}", e)))?;
+
+    Ok(serde_json::to_string(&issues)?)
+}
+
 /// Check if document is locked
 #[tauri::command]
 pub async fn is_document_locked(
@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+use serde::Deserialize;
+use sqlx::SqlitePool;
+use tauri::State;
+use crate::commands::amount_parser::DecimalSeparator;
+use crate::commands::column_mapping::{column_letters_to_index, ChequeField, ColumnRef};
+use crate::database::models::{DataError, MappingProfile};
+
+type Result<T> = std::result::Result<T, DataError>;
+
+/// Longest cheque number a numeric cell can hold without losing digits
+const MAX_CHEQUE_NUMBER_DIGITS: i64 = 15;
+
+/// Column mapping profile as entered by the user, for creating or updating one
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MappingProfileInput {
+    pub name: String,
+    pub sheet_name: Option<String>,
+    pub header_row: i64,
+    pub columns: HashMap<ChequeField, ColumnRef>,
+    pub decimal_separator: Option<DecimalSeparator>,
+    pub cheque_number_digits: Option<i64>,
+}
+
+fn validate_profile(profile: &MappingProfileInput) -> Result<String> {
+    let MappingProfileInput { name, header_row, columns, cheque_number_digits, .. } = profile;
+    if name.trim().is_empty() {
+        return Err(DataError::Custom("Profile name cannot be empty".to_string()));
+    }
+
+    if *header_row < 1 {
+        return Err(DataError::Custom("Header row must be 1 or greater".to_string()));
+    }
+
+    if let Some(digits) = cheque_number_digits {
+        if !(1..=MAX_CHEQUE_NUMBER_DIGITS).contains(digits) {
+            return Err(DataError::Custom(format!(
+                "Cheque number digits must be between 1 and {}",
+                MAX_CHEQUE_NUMBER_DIGITS
+            )));
+        }
+    }
+
+    for (field, column_ref) in columns {
+        match column_ref {
+            ColumnRef::Column(letters) if column_letters_to_index(letters).is_none() => {
+                return Err(DataError::Custom(format!(
+                    "Invalid column letter '{}' for {}",
+                    letters,
+                    field.as_str()
+                )));
+            }
+            ColumnRef::Header(header) if header.trim().is_empty() => {
+                return Err(DataError::Custom(format!(
+                    "Header name for {} cannot be empty",
+                    field.as_str()
+                )));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(serde_json::to_string(columns)?)
+}
+
+/// Get all column mapping profiles
+#[tauri::command]
+pub async fn get_mapping_profiles(pool: State<'_, SqlitePool>) -> Result<String> {
+    let profiles: Vec<MappingProfile> = sqlx::query_as::<_, MappingProfile>(
+        "SELECT id, name, sheet_name, header_row, column_map, decimal_separator, cheque_number_digits,
+                created_at, updated_at
+         FROM mapping_profiles ORDER BY name ASC"
+    )
+    .fetch_all(pool.inner())
+    .await?;
+
+    Ok(serde_json::to_string(&profiles)?)
+}
+
+/// Create a new column mapping profile
+#[tauri::command]
+pub async fn create_mapping_profile(
+    profile: MappingProfileInput,
+    pool: State<'_, SqlitePool>,
+) -> Result<i64> {
+    let column_map = validate_profile(&profile)?;
+
+    let result = sqlx::query(
+        "INSERT INTO mapping_profiles (name, sheet_name, header_row, column_map, decimal_separator, cheque_number_digits)
+         VALUES (?, ?, ?, ?, ?, ?)"
+    )
+    .bind(profile.name.trim())
+    .bind(profile.sheet_name.filter(|s| !s.trim().is_empty()))
+    .bind(profile.header_row)
+    .bind(column_map)
+    .bind(profile.decimal_separator.unwrap_or_default().as_str())
+    .bind(profile.cheque_number_digits)
+    .execute(pool.inner())
+    .await
+    .map_err(|e| DataError::Database(format!("Failed to create mapping profile: {}", e)))?;
+
+    Ok(result.last_insert_rowid())
+}
+
+/// Update an existing column mapping profile
+#[tauri::command]
+pub async fn update_mapping_profile(
+    id: i64,
+    profile: MappingProfileInput,
+    pool: State<'_, SqlitePool>,
+) -> Result<()> {
+    let column_map = validate_profile(&profile)?;
+
+    let rows_affected = sqlx::query(
+        "UPDATE mapping_profiles SET name = ?, sheet_name = ?, header_row = ?, column_map = ?,
+                decimal_separator = ?, cheque_number_digits = ?
+         WHERE id = ?"
+    )
+    .bind(profile.name.trim())
+    .bind(profile.sheet_name.filter(|s| !s.trim().is_empty()))
+    .bind(profile.header_row)
+    .bind(column_map)
+    .bind(profile.decimal_separator.unwrap_or_default().as_str())
+    .bind(profile.cheque_number_digits)
+    .bind(id)
+    .execute(pool.inner())
+    .await
+    .map_err(|e| DataError::Database(format!("Failed to update mapping profile: {}", e)))?
+    .rows_affected();
+
+    if rows_affected == 0 {
+        return Err(DataError::Custom(format!("Mapping profile with ID {} not found", id)));
+    }
+
+    Ok(())
+}
+
+/// Delete a column mapping profile
+#[tauri::command]
+pub async fn delete_mapping_profile(
+    id: i64,
+    pool: State<'_, SqlitePool>,
+) -> Result<()> {
+    let rows_affected = sqlx::query("DELETE FROM mapping_profiles WHERE id = ?")
+        .bind(id)
+        .execute(pool.inner())
+        .await
+        .map_err(|e| DataError::Database(format!("Failed to delete mapping profile: {}", e)))?
+        .rows_affected();
+
+    if rows_affected == 0 {
+        return Err(DataError::Custom(format!("Mapping profile with ID {} not found", id)));
+    }
+
+    Ok(())
+}
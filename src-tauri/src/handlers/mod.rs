@@ -1,3 +1,5 @@
 pub mod documents;
 pub mod cheques;
 pub mod kanban;
+pub mod mapping_profiles;
+pub mod settings;
@@ -0,0 +1,164 @@
+use sqlx::SqlitePool;
+use tauri::State;
+use crate::commands::app_settings::set_setting;
+use crate::commands::column_mapping::load_mapping;
+use crate::commands::currency::parse_currency;
+use crate::commands::duplicate_payments::{load_duplicate_rules, DuplicateRules, DUPLICATE_RULES_KEY};
+use crate::commands::file_signatures::{load_signature_policy, save_signature_policy, SignaturePolicy};
+use crate::commands::hot_folder::{load_hot_folder_config, HotFolderConfig, HOT_FOLDER_KEY, MIN_POLL_SECONDS};
+use crate::commands::number_words::{load_amount_words_settings, AmountWordsSettings, AMOUNT_WORDS_KEY};
+use crate::commands::payment_limits::{load_payment_limits, PaymentLimits, PAYMENT_LIMITS_KEY};
+use crate::database::models::DataError;
+
+type Result<T> = std::result::Result<T, DataError>;
+
+/// Get the duplicate payment detection rules
+#[tauri::command]
+pub async fn get_duplicate_payment_rules(pool: State<'_, SqlitePool>) -> Result<String> {
+    let mut conn = pool.acquire().await?;
+    let rules = load_duplicate_rules(&mut conn).await?;
+
+    Ok(serde_json::to_string(&rules)?)
+}
+
+/// Update the duplicate payment detection rules
+#[tauri::command]
+pub async fn update_duplicate_payment_rules(
+    rules: DuplicateRules,
+    pool: State<'_, SqlitePool>,
+) -> Result<()> {
+    if rules.same_payee_amount_days == Some(0) {
+        return Err(DataError::Custom(
+            "Payee and amount window must be at least 1 day; leave it empty to disable the rule".to_string(),
+        ));
+    }
+
+    let mut conn = pool.acquire().await?;
+    set_setting(&mut conn, DUPLICATE_RULES_KEY, &rules).await?;
+
+    Ok(())
+}
+
+/// Get the hot folder ingestion settings
+#[tauri::command]
+pub async fn get_hot_folder_config(pool: State<'_, SqlitePool>) -> Result<String> {
+    let mut conn = pool.acquire().await?;
+    let config = load_hot_folder_config(&mut conn).await?;
+
+    Ok(serde_json::to_string(&config)?)
+}
+
+/// Update the hot folder ingestion settings; the watcher picks them up on its next scan
+#[tauri::command]
+pub async fn update_hot_folder_config(
+    config: HotFolderConfig,
+    pool: State<'_, SqlitePool>,
+) -> Result<()> {
+    if config.poll_seconds < MIN_POLL_SECONDS {
+        return Err(DataError::Custom(format!(
+            "Hot folder must be polled at most every {} seconds",
+            MIN_POLL_SECONDS
+        )));
+    }
+
+    if let Some(code) = &config.currency {
+        parse_currency(code).map_err(DataError::Custom)?;
+    }
+
+    if config.enabled {
+        let path = config.path.as_deref().map(str::trim).unwrap_or_default();
+        if path.is_empty() {
+            return Err(DataError::Custom("Choose a folder to watch before enabling the hot folder".to_string()));
+        }
+        if !std::path::Path::new(path).is_dir() {
+            return Err(DataError::Custom(format!("Hot folder '{}' does not exist", path)));
+        }
+        // Fail now rather than on every file if the profile has gone
+        load_mapping(pool.inner(), config.profile_id).await?;
+    }
+
+    let mut conn = pool.acquire().await?;
+    set_setting(&mut conn, HOT_FOLDER_KEY, &config).await?;
+
+    Ok(())
+}
+
+/// Get the signature policy and trusted keys for payment files. HMAC secrets
+/// are left out; `has_secret` tells whether one is stored.
+#[tauri::command]
+pub async fn get_signature_policy(pool: State<'_, SqlitePool>) -> Result<String> {
+    let mut conn = pool.acquire().await?;
+    let policy = load_signature_policy(&mut conn).await?;
+
+    Ok(serde_json::to_string(&policy.redacted())?)
+}
+
+/// Update the signature policy and trusted keys; applies to the next import.
+/// Leave an HMAC secret blank to keep the stored one.
+#[tauri::command]
+pub async fn update_signature_policy(
+    policy: SignaturePolicy,
+    pool: State<'_, SqlitePool>,
+) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    save_signature_policy(&mut tx, policy).await?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Get the numbering systems and amount line format used for amounts in words
+#[tauri::command]
+pub async fn get_amount_words_settings(pool: State<'_, SqlitePool>) -> Result<String> {
+    let mut conn = pool.acquire().await?;
+    let settings = load_amount_words_settings(&mut conn).await?;
+
+    Ok(serde_json::to_string(&settings)?)
+}
+
+/// Update the numbering systems and amount line format used for amounts in words
+#[tauri::command]
+pub async fn update_amount_words_settings(
+    mut settings: AmountWordsSettings,
+    pool: State<'_, SqlitePool>,
+) -> Result<()> {
+    if settings.account_numbering.keys().any(|account| account.trim().is_empty()) {
+        return Err(DataError::Custom("Enter the account number for each account numbering system".to_string()));
+    }
+    settings.amount_line.validate()?;
+    settings.account_numbering = settings
+        .account_numbering
+        .into_iter()
+        .map(|(account, system)| (account.trim().to_string(), system))
+        .collect();
+
+    let mut conn = pool.acquire().await?;
+    set_setting(&mut conn, AMOUNT_WORDS_KEY, &settings).await?;
+
+    Ok(())
+}
+
+/// Get the maximum cheque amounts in each currency, overall, by account and by role
+#[tauri::command]
+pub async fn get_payment_limits(pool: State<'_, SqlitePool>) -> Result<String> {
+    let mut conn = pool.acquire().await?;
+    let limits = load_payment_limits(&mut conn).await?;
+
+    Ok(serde_json::to_string(&limits)?)
+}
+
+/// Update the maximum cheque amounts; they apply to imports, corrections and
+/// approvals from now on
+#[tauri::command]
+pub async fn update_payment_limits(
+    limits: PaymentLimits,
+    pool: State<'_, SqlitePool>,
+) -> Result<()> {
+    limits.validate()?;
+    let limits = limits.normalized();
+
+    let mut conn = pool.acquire().await?;
+    set_setting(&mut conn, PAYMENT_LIMITS_KEY, &limits).await?;
+
+    Ok(())
+}
@@ -10,9 +10,23 @@ mod handlers;
 // Import all command handlers
 use commands::{
     convert_amount_to_words,
+    cheque_amount_in_words,
+    get_currencies,
     parse_excel_to_cheques,
+    list_spreadsheet_sheets,
     upload_excel_as_blob,
-    open_excel_from_database
+    open_excel_from_database,
+    start_import_job,
+    cancel_import_job,
+    stage_import,
+    get_staged_imports,
+    get_staged_rows,
+    update_staged_row,
+    delete_staged_row,
+    discard_staged_import,
+    commit_staged_import,
+    generate_import_template,
+    ImportJobs
 };
 
 use handlers::{
@@ -23,15 +37,19 @@ use handlers::{
         delete_document,
         delete_all_documents,
         lock_document,
-        is_document_locked
+        is_document_locked,
+        get_import_issues
     },
     cheques::{
         get_all_cheques,
         update_cheque_status,
         update_cheque_issue_date,
+        update_cheque_numbering_system,
         update_decline_reason,
         increment_print_count,
-        execute_dynamic_query
+        execute_dynamic_query,
+        override_duplicate_payment,
+        get_amount_conversion_issues
     },
     kanban::{
         get_kanban_notes,
@@ -42,6 +60,24 @@ use handlers::{
         get_kanban_comments,
         create_kanban_comment,
         delete_kanban_comment
+    },
+    mapping_profiles::{
+        get_mapping_profiles,
+        create_mapping_profile,
+        update_mapping_profile,
+        delete_mapping_profile
+    },
+    settings::{
+        get_duplicate_payment_rules,
+        update_duplicate_payment_rules,
+        get_hot_folder_config,
+        update_hot_folder_config,
+        get_signature_policy,
+        update_signature_policy,
+        get_amount_words_settings,
+        update_amount_words_settings,
+        get_payment_limits,
+        update_payment_limits
     }
 };
 
@@ -74,17 +110,55 @@ async fn async_main() {
         Err(e) => println!("✗ Migration error: {}", e),
     }
 
+    // Hash documents uploaded before duplicate detection existed
+    match commands::document_hash::backfill_document_hashes(&pool).await {
+        Ok(0) => {}
+        Ok(count) => println!("✓ Backfilled content hashes for {} document(s)", count),
+        Err(e) => println!("✗ Document hash backfill failed: {}", e),
+    }
+
+    // Amounts rounded when they moved to minor units need a human to review them
+    match sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM amount_conversion_issues")
+        .fetch_one(&pool)
+        .await
+    {
+        Ok(0) => {}
+        Ok(count) => println!("⚠ {} cheque amount(s) were rounded to whole minor units; review them with get_amount_conversion_issues", count),
+        Err(e) => println!("✗ Failed to check amount conversion issues: {}", e),
+    }
+
     // Start Tauri application
+    let watcher_pool = pool.clone();
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_shell::init())
+        .setup(move |app| {
+            // Import files dropped into the configured hot folder
+            commands::hot_folder::start_hot_folder_watcher(app.handle().clone(), watcher_pool);
+            Ok(())
+        })
         .manage(pool)
+        .manage(ImportJobs::default())
         .invoke_handler(generate_handler![
             // Excel processing
             parse_excel_to_cheques,
+            list_spreadsheet_sheets,
             convert_amount_to_words,
+            cheque_amount_in_words,
+            get_currencies,
             upload_excel_as_blob,
             open_excel_from_database,
+            start_import_job,
+            cancel_import_job,
+            // Import staging
+            stage_import,
+            get_staged_imports,
+            get_staged_rows,
+            update_staged_row,
+            delete_staged_row,
+            discard_staged_import,
+            commit_staged_import,
+            generate_import_template,
             // Document management
             get_all_documents,
             download_document_to_folder,
@@ -93,13 +167,17 @@ async fn async_main() {
             delete_all_documents,
             lock_document,
             is_document_locked,
+            get_import_issues,
             // Cheque operations
             get_all_cheques,
             update_cheque_status,
             update_cheque_issue_date,
+            update_cheque_numbering_system,
             update_decline_reason,
             increment_print_count,
             execute_dynamic_query,
+            override_duplicate_payment,
+            get_amount_conversion_issues,
             // Kanban board
             get_kanban_notes,
             create_kanban_note,
@@ -108,7 +186,23 @@ async fn async_main() {
             delete_kanban_note,
             get_kanban_comments,
             create_kanban_comment,
-            delete_kanban_comment
+            delete_kanban_comment,
+            // Column mapping profiles
+            get_mapping_profiles,
+            create_mapping_profile,
+            update_mapping_profile,
+            delete_mapping_profile,
+            // Settings
+            get_duplicate_payment_rules,
+            update_duplicate_payment_rules,
+            get_hot_folder_config,
+            update_hot_folder_config,
+            get_signature_policy,
+            update_signature_policy,
+            get_amount_words_settings,
+            update_amount_words_settings,
+            get_payment_limits,
+            update_payment_limits
         ])
         .run(generate_context!())
         .expect("Error running Tauri application");
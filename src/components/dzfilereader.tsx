@@ -13,6 +13,7 @@ const Basic: FC = () => {
     null
   );
   const [filename, setFileName] = useState<String>("");
+  const [password, setPassword] = useState<string | undefined>(undefined);
   const { clearActiveCheques } = useChequeStore();
 
   const onDrop = useCallback((acceptedFiles: File[]) => {
@@ -28,14 +29,42 @@ const Basic: FC = () => {
         const data = event.target?.result as ArrayBuffer;
         setExcelDataArray(data);
 
-        // Send the file data to the Tauri backend
+        // Send the file data to the Tauri backend, asking for the password
+        // of an encrypted workbook until it opens or the user gives up
         console.log("Data,", data);
-        const process_data: string = await invoke("parse_excel_to_cheques", {
-          fileData: Array.from(new Uint8Array(data)),
-          fileName: file.name,
-        });
+        let filePassword: string | undefined = undefined;
+        let process_data: string;
+        for (;;) {
+          try {
+            process_data = await invoke("parse_excel_to_cheques", {
+              fileData: Array.from(new Uint8Array(data)),
+              fileName: file.name,
+              options: { password: filePassword },
+            });
+            break;
+          } catch (error: any) {
+            const message = error?.toString() ?? "";
+            if (!message.includes("password protected") && !message.includes("Incorrect password")) {
+              toast.error(message || "Failed to read file");
+              return;
+            }
+            const entered = window.prompt(
+              message.includes("Incorrect password")
+                ? `Incorrect password for ${file.name}. Try again:`
+                : `${file.name} is password protected. Enter its password:`
+            );
+            if (!entered) return;
+            filePassword = entered;
+          }
+        }
+        setPassword(filePassword);
         let returnedData = JSON.parse(process_data);
-        setInvokedExcelData(returnedData);
+        if (returnedData.rejected_count > 0) {
+          toast.error(
+            `${returnedData.rejected_count} row(s) failed validation and will not be imported`
+          );
+        }
+        setInvokedExcelData(returnedData.cheques);
       };
       reader.readAsArrayBuffer(file);
     }
@@ -80,6 +109,7 @@ const Basic: FC = () => {
           columns={columns}
           data={invokedExcelData}
           filename={filename}
+          password={password}
         />
       ) : null}
     </div>
@@ -22,14 +22,86 @@ import {
 import { Input } from "./ui/input";
 import { Button } from "./ui/button";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { useChequeStore } from "@/store/chequeStore";
 import { ChequeType } from "@/type";
 
+type ImportProgressEvent = {
+  job_id: string;
+  stage: "parsing" | "checking_duplicates" | "inserting";
+  rows_total: number;
+  rows_parsed: number;
+  rows_inserted: number;
+  errors: number;
+};
+
+type ImportFinishedEvent = {
+  job_id: string;
+  status: "completed" | "failed" | "cancelled";
+  result: any;
+  error: string | null;
+};
+
+// Name and options of an upload, sent beside the raw file so it is not copied into a JSON array
+const uploadHeaders = (fileName: string, options: object) => {
+  const header = new TextEncoder().encode(JSON.stringify({ fileName, options }));
+  return { "import-upload": btoa(Array.from(header, (byte) => String.fromCharCode(byte)).join("")) };
+};
+
+// Run an import in the background, showing its progress in a loading toast
+const runImportJob = async (
+  data: Uint8Array,
+  fileName: string,
+  password?: string
+): Promise<any> => {
+  const toastId = toast.loading(`Importing ${fileName}...`);
+  let jobId: string | null = null;
+  let finished: ImportFinishedEvent | null = null;
+  let settle: ((event: ImportFinishedEvent) => void) | null = null;
+
+  const unlistenProgress = await listen<ImportProgressEvent>("import-progress", ({ payload }) => {
+    if (payload.job_id !== jobId) return;
+    const detail =
+      payload.stage === "inserting"
+        ? `${payload.rows_inserted} of ${payload.rows_total} rows saved`
+        : payload.stage === "checking_duplicates"
+          ? `Checking ${payload.rows_total} rows for duplicate payments`
+          : `${payload.rows_parsed} rows read, ${payload.errors} error(s)`;
+    toast.loading(`Importing ${fileName}: ${detail}`, { id: toastId });
+  });
+  const unlistenFinished = await listen<ImportFinishedEvent>("import-finished", ({ payload }) => {
+    if (jobId === null || payload.job_id === jobId) {
+      finished = payload;
+      settle?.(payload);
+    }
+  });
+
+  try {
+    jobId = await invoke<string>("start_import_job", data, {
+      headers: uploadHeaders(fileName, { password }),
+    });
+    // The job can finish before start_import_job returns its id
+    const early = finished as ImportFinishedEvent | null;
+    const outcome = await new Promise<ImportFinishedEvent>((resolve) => {
+      if (early && early.job_id === jobId) resolve(early);
+      else settle = resolve;
+    });
+    if (outcome.status !== "completed") throw outcome.error ?? "Import failed";
+    return outcome.result;
+  } finally {
+    toast.dismiss(toastId);
+    unlistenProgress();
+    unlistenFinished();
+  }
+};
+
 type TaskTableProps<TData, TValue> = {
   columns: ColumnDef<TData, TValue>[];
   data: TData[];
   excelDataArray?: ArrayBuffer | null;
   filename: String;
+  /** Password the previewed workbook was opened with, if it is encrypted */
+  password?: string;
 };
 
 export function TaskTable<TData, TValue>({
@@ -37,6 +109,7 @@ export function TaskTable<TData, TValue>({
   data,
   excelDataArray,
   filename,
+  password,
 }: TaskTableProps<TData, TValue>) {
   const { setActiveCheques, checkForDuplicates } = useChequeStore();
   const [sorting, setSorting] = useState<SortingState>([]);
@@ -85,12 +158,11 @@ export function TaskTable<TData, TValue>({
         return;
       }
 
-      const response: string = await invoke("upload_excel_as_blob", {
-        data: Array.from(new Uint8Array(excelDataArray)),
-        fileName: filename,
-      });
-
-      const responseData = JSON.parse(response);
+      const responseData = await runImportJob(
+        new Uint8Array(excelDataArray),
+        filename.toString(),
+        password
+      );
       const documentId = responseData.document_id || Date.now();
 
       setActiveCheques(chequeData, documentId, filename.toString());
@@ -0,0 +1,99 @@
+import { invoke } from "@tauri-apps/api/core";
+
+/** Currency of cheques that do not name one, as in the backend */
+export const DEFAULT_CURRENCY = "USD";
+
+/** Decimal places of each currency's minor unit, by ISO code: 2 for cents, 0 for the yen */
+export type MinorDigits = Record<string, number>;
+
+/** Cheque as returned by the backend, with its amount as an exact decimal string */
+type ChequeWireAmount<T> = Omit<T, "amount"> & { amount: string | number };
+
+/** Amount of a cheque as stored: whole minor units of its currency */
+interface MinorAmount {
+  amount_minor: number;
+  currency: string;
+}
+
+/** Turn the exact decimal amount the backend sends into a number for display and comparisons */
+export function withNumericAmount<T extends { amount: number }>(cheque: ChequeWireAmount<T>): T {
+  return { ...cheque, amount: Number(cheque.amount) } as T;
+}
+
+let minorDigitsRequest: Promise<MinorDigits> | null = null;
+
+/** Minor-unit decimal places from the backend's currency registry, fetched once */
+export function loadMinorDigits(): Promise<MinorDigits> {
+  minorDigitsRequest ??= invoke<string>("get_currencies")
+    .then((response) => {
+      const currencies: { code: string; minor_digits: number }[] = JSON.parse(response);
+      return Object.fromEntries(currencies.map((currency) => [currency.code, currency.minor_digits]));
+    })
+    .catch((error) => {
+      minorDigitsRequest = null;
+      throw error;
+    });
+  return minorDigitsRequest;
+}
+
+/** `minor` units of `currency` in major units; unknown currencies are read as the default one, as the backend does */
+export function toMajorUnits(minor: number, currency: string, minorDigits: MinorDigits): number {
+  const digits = minorDigits[currency.trim().toUpperCase()] ?? minorDigits[DEFAULT_CURRENCY];
+  if (digits === undefined) {
+    throw new Error(`Minor units of ${currency} are not known`);
+  }
+  return minor / 10 ** digits;
+}
+
+/** Total of the amounts in each currency, in major units, summed exactly in minor units. Amounts in different currencies are never added together. */
+export function sumAmounts(amounts: MinorAmount[], minorDigits: MinorDigits): Record<string, number> {
+  const totals: Record<string, number> = {};
+  for (const { amount_minor, currency } of amounts) {
+    const code = currency.trim().toUpperCase();
+    totals[code] = (totals[code] ?? 0) + amount_minor;
+  }
+  return Object.fromEntries(
+    Object.entries(totals).map(([currency, minor]) => [currency, toMajorUnits(minor, currency, minorDigits)])
+  );
+}
+
+/** Total of the amounts in `currency`, ignoring amounts in any other currency */
+export function sumInCurrency(amounts: MinorAmount[], currency: string, minorDigits: MinorDigits): number {
+  return sumAmounts(amounts, minorDigits)[currency.trim().toUpperCase()] ?? 0;
+}
+
+/** `sumInCurrency` of each group of amounts, by the key `keyOf` gives; amounts without a key are skipped */
+export function sumInCurrencyBy<T extends MinorAmount>(
+  amounts: T[],
+  currency: string,
+  keyOf: (amount: T) => string | null,
+  minorDigits: MinorDigits
+): Record<string, number> {
+  const groups: Record<string, T[]> = {};
+  for (const amount of amounts) {
+    const key = keyOf(amount);
+    if (key !== null) {
+      (groups[key] ||= []).push(amount);
+    }
+  }
+  return Object.fromEntries(
+    Object.entries(groups).map(([key, group]) => [key, sumInCurrency(group, currency, minorDigits)])
+  );
+}
+
+/** `amount` with the decimal places of `currency` and its code, such as "1,200.50 USD" or "900,000 JPY" */
+export function formatAmount(amount: number, currency: string, minorDigits: MinorDigits): string {
+  const code = currency.trim().toUpperCase();
+  const digits = minorDigits[code] ?? minorDigits[DEFAULT_CURRENCY];
+  const fractionDigits = digits === undefined ? {} : { minimumFractionDigits: digits, maximumFractionDigits: digits };
+  return `${amount.toLocaleString("en-US", fractionDigits)} ${code}`;
+}
+
+/** Totals from `sumAmounts`, one per currency, such as "100.00 USD, 100 JPY" */
+export function formatTotals(totals: Record<string, number>, minorDigits: MinorDigits): string {
+  const entries = Object.entries(totals);
+  if (entries.length === 0) {
+    return formatAmount(0, DEFAULT_CURRENCY, minorDigits);
+  }
+  return entries.map(([currency, total]) => formatAmount(total, currency, minorDigits)).join(", ");
+}
@@ -7,6 +7,7 @@ import { Input } from "@/components/ui/input";
 import { Download, Search, TrendingUp, DollarSign, Users, FileText, CircleCheck as CheckCircle, Circle as XCircle, Clock, Printer, ChevronUp, ChevronDown, ChevronsUpDown } from "lucide-react";
 import { BarChart, Bar, LineChart, Line, PieChart, Pie, Cell, XAxis, YAxis, CartesianGrid, Tooltip, ResponsiveContainer } from 'recharts';
 import { format, startOfMonth, endOfMonth, startOfYear, endOfYear, isWithinInterval, parseISO, subMonths } from 'date-fns';
+import { DEFAULT_CURRENCY, formatAmount, loadMinorDigits, MinorDigits, sumInCurrency, sumInCurrencyBy, withNumericAmount } from "@/lib/money";
 
 export const Route = createFileRoute("/analysis")({
   component: RouteComponent,
@@ -16,6 +17,8 @@ interface ChequeAnalytics {
   cheque_id: number;
   cheque_number: string;
   amount: number;
+  amount_minor: number;
+  currency: string;
   client_name: string;
   status: string;
   issue_date: string;
@@ -24,6 +27,16 @@ interface ChequeAnalytics {
   created_at: string;
 }
 
+/** Cheque amount rounded to whole minor units when amounts stopped being stored as REAL */
+interface AmountConversionIssue {
+  id: number;
+  cheque_id: number;
+  cheque_number: string | null;
+  currency: string;
+  original_amount: number;
+  amount: string;
+}
+
 type DateFilter = 'all' | 'day' | 'month' | 'year' | 'custom';
 type AnalyticsCategory = 'total-cheques' | 'avg-amount' | 'amount-by-month' | 'top-clients' | 'approval-rate' | 'pending-approvals' | 'high-value' | 'declined-analysis' | 'print-status' | 'approved-vs-declined' | 'unsigned-high-value' | 'cheques-by-handler';
 
@@ -39,6 +52,9 @@ function RouteComponent() {
   const [customDateTo, setCustomDateTo] = useState<Date | undefined>();
   const [searchQuery, setSearchQuery] = useState("");
   const [cheques, setCheques] = useState<ChequeAnalytics[]>([]);
+  const [minorDigits, setMinorDigits] = useState<MinorDigits>({});
+  const [conversionIssues, setConversionIssues] = useState<AmountConversionIssue[]>([]);
+  const [selectedCurrency, setSelectedCurrency] = useState<string | null>(null);
   const [filteredData, setFilteredData] = useState<ChequeAnalytics[]>([]);
   const [currentPage, setCurrentPage] = useState(1);
   const [loading, setLoading] = useState(false);
@@ -65,9 +81,10 @@ function RouteComponent() {
     const fetchCheques = async () => {
       setLoading(true);
       try {
-        const response = await invoke<string>("get_all_cheques");
+        const [response, digits] = await Promise.all([invoke<string>("get_all_cheques"), loadMinorDigits()]);
         const data = JSON.parse(response);
-        setCheques(data.cheques || []);
+        setMinorDigits(digits);
+        setCheques((data.cheques || []).map(withNumericAmount<ChequeAnalytics>));
       } catch (error) {
         console.error("Failed to fetch cheques:", error);
       } finally {
@@ -78,8 +95,27 @@ function RouteComponent() {
     fetchCheques();
   }, []);
 
+  useEffect(() => {
+    invoke<string>("get_amount_conversion_issues")
+      .then(response => setConversionIssues(JSON.parse(response)))
+      .catch(error => console.error("Failed to fetch amount conversion issues:", error));
+  }, []);
+
+  // Amounts in different currencies cannot be added, so analytics cover one currency at a time,
+  // the most used one unless another is chosen
+  const currencyOptions = useMemo(() => {
+    const counts = cheques.reduce((acc, c) => {
+      acc[c.currency] = (acc[c.currency] || 0) + 1;
+      return acc;
+    }, {} as Record<string, number>);
+    return Object.entries(counts).sort(([, a], [, b]) => b - a).map(([currency]) => currency);
+  }, [cheques]);
+  const currency = selectedCurrency && currencyOptions.includes(selectedCurrency)
+    ? selectedCurrency
+    : currencyOptions[0] ?? DEFAULT_CURRENCY;
+
   const getDateFilteredData = useMemo(() => {
-    let data = [...cheques];
+    let data = cheques.filter(c => c.currency === currency);
     const now = new Date();
 
     if (dateFilter === 'day') {
@@ -122,7 +158,7 @@ function RouteComponent() {
     }
 
     return data;
-  }, [cheques, dateFilter, selectedMonth, customDateFrom, customDateTo]);
+  }, [cheques, currency, dateFilter, selectedMonth, customDateFrom, customDateTo]);
 
   const chartData = useMemo(() => {
     const data = getDateFilteredData;
@@ -134,7 +170,7 @@ function RouteComponent() {
         return { value: data.length, type: 'number' };
 
       case 'avg-amount':
-        return { value: data.length ? data.reduce((sum, c) => sum + c.amount, 0) / data.length : 0, type: 'currency' };
+        return { value: data.length ? sumInCurrency(data, currency, minorDigits) / data.length : 0, type: 'currency' };
 
       case 'amount-by-month':
         const limitedData = dateFilter === 'all' || dateFilter === 'year'
@@ -149,23 +185,19 @@ function RouteComponent() {
             })
           : data;
 
-        const amountByMonth = limitedData.reduce((acc, c) => {
-          if (!c.issue_date) return acc;
+        const amountByMonth = sumInCurrencyBy(limitedData, currency, c => {
+          if (!c.issue_date) return null;
           try {
-            const month = format(parseISO(c.issue_date), 'MMM yyyy');
-            acc[month] = (acc[month] || 0) + c.amount;
+            return format(parseISO(c.issue_date), 'MMM yyyy');
           } catch {
             // Skip invalid dates
+            return null;
           }
-          return acc;
-        }, {} as Record<string, number>);
+        }, minorDigits);
         return { data: Object.entries(amountByMonth).map(([name, value]) => ({ name, value })), type: 'line' };
 
       case 'top-clients':
-        const clientAmounts = data.reduce((acc, c) => {
-          acc[c.client_name] = (acc[c.client_name] || 0) + c.amount;
-          return acc;
-        }, {} as Record<string, number>);
+        const clientAmounts = sumInCurrencyBy(data, currency, c => c.client_name, minorDigits);
         return {
           data: Object.entries(clientAmounts)
             .sort(([, a], [, b]) => b - a)
@@ -191,7 +223,7 @@ function RouteComponent() {
         const pending = data.filter(c => c.status === 'Pending');
         return {
           value: pending.length,
-          amount: pending.reduce((sum, c) => sum + c.amount, 0),
+          amount: sumInCurrency(pending, currency, minorDigits),
           type: 'number'
         };
 
@@ -199,7 +231,7 @@ function RouteComponent() {
         const highValue = data.filter(c => c.amount > 1500);
         return {
           value: highValue.length,
-          amount: highValue.reduce((sum, c) => sum + c.amount, 0),
+          amount: sumInCurrency(highValue, currency, minorDigits),
           type: 'number'
         };
 
@@ -207,7 +239,7 @@ function RouteComponent() {
         const declinedCheques = data.filter(c => c.status === 'Declined');
         return {
           value: declinedCheques.length,
-          amount: declinedCheques.reduce((sum, c) => sum + c.amount, 0),
+          amount: sumInCurrency(declinedCheques, currency, minorDigits),
           type: 'number'
         };
 
@@ -223,8 +255,8 @@ function RouteComponent() {
         };
 
       case 'approved-vs-declined':
-        const approvedAmount = data.filter(c => c.status === 'Approved').reduce((sum, c) => sum + c.amount, 0);
-        const declinedAmount = data.filter(c => c.status === 'Declined').reduce((sum, c) => sum + c.amount, 0);
+        const approvedAmount = sumInCurrency(data.filter(c => c.status === 'Approved'), currency, minorDigits);
+        const declinedAmount = sumInCurrency(data.filter(c => c.status === 'Declined'), currency, minorDigits);
         return {
           data: [
             { name: 'Approved', value: approvedAmount },
@@ -237,7 +269,7 @@ function RouteComponent() {
         const unsigned = data.filter(c => c.amount > 1500 && (!c.print_count || c.print_count === 0));
         return {
           value: unsigned.length,
-          amount: unsigned.reduce((sum, c) => sum + c.amount, 0),
+          amount: sumInCurrency(unsigned, currency, minorDigits),
           type: 'number'
         };
 
@@ -258,7 +290,7 @@ function RouteComponent() {
       default:
         return { value: 0, type: 'number' };
     }
-  }, [selectedCategory, getDateFilteredData, dateFilter]);
+  }, [selectedCategory, getDateFilteredData, dateFilter, currency, minorDigits]);
 
   useEffect(() => {
     const filtered = getDateFilteredData;
@@ -412,6 +444,25 @@ function RouteComponent() {
         <p className="text-muted-foreground mt-1">Comprehensive cheque analytics and insights</p>
       </div>
 
+      {conversionIssues.length > 0 && (
+        <Card className="mb-6 border-yellow-500">
+          <CardHeader className="pb-2">
+            <CardTitle className="text-sm font-medium">
+              {conversionIssues.length} cheque amount(s) were rounded when amounts moved to minor units
+            </CardTitle>
+          </CardHeader>
+          <CardContent>
+            <ul className="text-sm space-y-1 max-h-40 overflow-y-auto">
+              {conversionIssues.map(issue => (
+                <li key={issue.id}>
+                  Cheque {issue.cheque_number ?? `#${issue.cheque_id}`}: {issue.original_amount} {issue.currency} is now {issue.amount} {issue.currency}
+                </li>
+              ))}
+            </ul>
+          </CardContent>
+        </Card>
+      )}
+
       <div className="grid gap-4 mb-6 md:grid-cols-2 lg:grid-cols-4">
         <Card>
           <CardHeader className="pb-2">
@@ -430,6 +481,26 @@ function RouteComponent() {
           </CardContent>
         </Card>
 
+        <Card>
+          <CardHeader className="pb-2">
+            <CardTitle className="text-sm font-medium">Currency</CardTitle>
+          </CardHeader>
+          <CardContent>
+            <select
+              value={currency}
+              onChange={(e) => {
+                setSelectedCurrency(e.target.value);
+                setCurrentPage(1);
+              }}
+              className="w-full border border-input rounded-md px-3 py-2 text-sm bg-background"
+            >
+              {(currencyOptions.length ? currencyOptions : [currency]).map(code => (
+                <option key={code} value={code}>{code}</option>
+              ))}
+            </select>
+          </CardContent>
+        </Card>
+
         <Card>
           <CardHeader className="pb-2">
             <CardTitle className="text-sm font-medium">Date Filter</CardTitle>
@@ -525,7 +596,7 @@ function RouteComponent() {
               </div>
               {chartData.amount !== undefined && (
                 <div className="text-2xl text-muted-foreground">
-                  ${chartData.amount.toLocaleString('en-US', { minimumFractionDigits: 2 })}
+                  {formatAmount(chartData.amount, currency, minorDigits)}
                 </div>
               )}
             </div>
@@ -534,9 +605,7 @@ function RouteComponent() {
           {chartData.type === 'currency' && (
             <div className="text-center py-8">
               <div className="text-5xl font-bold">
-                ${chartData.value !== undefined && typeof chartData.value === 'number'
-                  ? chartData.value.toLocaleString('en-US', { minimumFractionDigits: 2 })
-                  : '0.00'}
+                {formatAmount(typeof chartData.value === 'number' ? chartData.value : 0, currency, minorDigits)}
               </div>
             </div>
           )}
@@ -547,7 +616,7 @@ function RouteComponent() {
                 <CartesianGrid strokeDasharray="3 3" />
                 <XAxis dataKey="name" />
                 <YAxis />
-                <Tooltip formatter={(value: number) => selectedCategory === 'cheques-by-handler' ? value : `$${value.toLocaleString()}`} />
+                <Tooltip formatter={(value: number) => selectedCategory === 'cheques-by-handler' ? value : formatAmount(value, currency, minorDigits)} />
                 <Bar dataKey="value" fill="#3b82f6" />
               </BarChart>
             </ResponsiveContainer>
@@ -559,7 +628,7 @@ function RouteComponent() {
                 <CartesianGrid strokeDasharray="3 3" />
                 <XAxis dataKey="name" />
                 <YAxis />
-                <Tooltip formatter={(value: number) => `$${value.toLocaleString()}`} />
+                <Tooltip formatter={(value: number) => formatAmount(value, currency, minorDigits)} />
                 <Line type="monotone" dataKey="value" stroke="#3b82f6" strokeWidth={2} />
               </LineChart>
             </ResponsiveContainer>
@@ -683,7 +752,7 @@ function RouteComponent() {
                     <td className="p-3 text-sm">{cheque.cheque_number}</td>
                     <td className="p-3 text-sm">{cheque.client_name}</td>
                     <td className="p-3 text-sm text-right font-medium">
-                      ${cheque.amount.toLocaleString('en-US', { minimumFractionDigits: 2 })}
+                      {formatAmount(cheque.amount, cheque.currency, minorDigits)}
                     </td>
                     <td className="p-3 text-sm">
                       <span className={`px-2 py-1 rounded text-xs font-medium ${
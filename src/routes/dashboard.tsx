@@ -21,6 +21,8 @@ import {
 } from "lucide-react";
 import { format } from "date-fns";
 import { ChequeData } from "@/types";
+import { formatAmount, formatTotals, loadMinorDigits, MinorDigits, sumAmounts, withNumericAmount } from "@/lib/money";
+import { useUserStore } from "@/store/userStore";
 import toast from "react-hot-toast";
 import {
   Dialog,
@@ -99,7 +101,9 @@ function DatePickerCell({
 }
 
 function RouteComponent() {
+  const userId = useUserStore((state) => state.userId);
   const [cheques, setCheques] = useState<ChequeData[]>([]);
+  const [minorDigits, setMinorDigits] = useState<MinorDigits>({});
   const [loading, setLoading] = useState(true);
   const [searchQuery, setSearchQuery] = useState("");
   const [selectedCheques, setSelectedCheques] = useState<Set<number>>(
@@ -124,9 +128,11 @@ function RouteComponent() {
 
   const fetchCheques = async () => {
     try {
-      const response = await invoke<string>("get_all_cheques");
+      const [response, digits] = await Promise.all([invoke<string>("get_all_cheques"), loadMinorDigits()]);
       const data = JSON.parse(response);
-      setCheques(data.cheques || []);
+      data.cheques = (data.cheques || []).map(withNumericAmount<ChequeData>);
+      setMinorDigits(digits);
+      setCheques(data.cheques);
 
       const grouped = data.cheques?.reduce((acc: any, cheque: ChequeData) => {
         const key = cheque.document_id;
@@ -205,6 +211,7 @@ function RouteComponent() {
         chequeId,
         newStatus,
         ...(remarks && { remarks }),
+        userId,
       });
 
       setCheques((prev) =>
@@ -796,10 +803,7 @@ function RouteComponent() {
                         </div>
                       </td>
                       <td className="p-2 md:p-3 font-semibold text-xs md:text-sm">
-                        $
-                        {cheque.amount.toLocaleString("en-US", {
-                          minimumFractionDigits: 2,
-                        })}
+                        {formatAmount(cheque.amount, cheque.currency, minorDigits)}
                       </td>
                       <td className="p-2 md:p-3">
                         <DatePickerCell
@@ -898,10 +902,7 @@ function RouteComponent() {
             const required = c.amount > 1500 ? 2 : 1;
             return (c.current_signatures || 0) < required;
           });
-          const totalAmount = printableCheques.reduce(
-            (sum, c) => sum + c.amount,
-            0
-          );
+          const totals = sumAmounts(printableCheques, minorDigits);
 
           return (
             <div className="fixed inset-0 bg-black/50 flex items-center justify-center z-50">
@@ -912,10 +913,7 @@ function RouteComponent() {
                 <CardContent>
                   <div className="mb-4">
                     <div className="text-lg font-semibold mb-2">
-                      Total Amount: $
-                      {totalAmount.toLocaleString("en-US", {
-                        minimumFractionDigits: 2,
-                      })}
+                      Total Amount: {formatTotals(totals, minorDigits)}
                     </div>
                     <div className="text-sm text-muted-foreground mb-4">
                       {printableCheques.length} cheque(s) ready to print
@@ -941,10 +939,7 @@ function RouteComponent() {
                             </div>
                             <div className="text-right">
                               <div className="font-semibold">
-                                $
-                                {cheque.amount.toLocaleString("en-US", {
-                                  minimumFractionDigits: 2,
-                                })}
+                                {formatAmount(cheque.amount, cheque.currency, minorDigits)}
                               </div>
                               <div className="text-xs text-green-600">
                                 {cheque.current_signatures}/
@@ -986,10 +981,7 @@ function RouteComponent() {
                               </div>
                               <div className="text-right">
                                 <div className="font-semibold text-yellow-900">
-                                  $
-                                  {cheque.amount.toLocaleString("en-US", {
-                                    minimumFractionDigits: 2,
-                                  })}
+                                  {formatAmount(cheque.amount, cheque.currency, minorDigits)}
                                 </div>
                                 <div className="text-xs text-red-600">
                                   {current}/{required} signatures (missing{" "}
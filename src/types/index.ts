@@ -11,6 +11,9 @@ export interface ChequeData {
   created_at: string;
   cheque_number: string;
   amount: number;
+  /** Amount in minor units of `currency`, such as cents */
+  amount_minor: number;
+  currency: string;
   client_name: string;
   status: string;
   issue_date?: string;